@@ -0,0 +1,49 @@
+#!/bin/sh
+# Compares the compile time of large hex strings written as byte arrays (`hex!`) and as byte
+# string literals (`hex!(byte_str ...)`).
+#
+# Usage: benches/large_strings.sh [SIZE_IN_KIB ...]
+#
+# Each size is compiled in a fresh crate depending on this checkout, after the dependencies
+# are built once, so only the time spent on the crate with the hex string is measured. The
+# reported times are the wall-clock times of `cargo build` in the debug profile.
+set -eu
+
+root=$(cd "$(dirname "$0")/.." && pwd)
+dir=$(mktemp -d)
+trap 'rm -rf "$dir"' EXIT
+
+cat > "$dir/Cargo.toml" <<TOML
+[package]
+name = "large-strings"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+hex-magic = { path = "$root" }
+
+[workspace]
+TOML
+mkdir "$dir/src"
+echo 'pub const BYTES: [u8; 1] = hex_magic::hex!("00");' > "$dir/src/lib.rs"
+cargo build --quiet --manifest-path "$dir/Cargo.toml"
+
+if [ $# -eq 0 ]; then
+    set -- 64 256 1024
+fi
+
+printf '%8s %12s %12s\n' KiB hex byte_str
+for kib in "$@"; do
+    digits=$(head -c $((kib * 1024)) /dev/urandom | od -An -v -tx1 | tr -d ' \n')
+    times=""
+    for modifier in "" "byte_str "; do
+        printf 'pub const BYTES: [u8; %d] = hex_magic::hex!(%s"%s");\n' \
+            $((kib * 1024)) "$modifier" "$digits" > "$dir/src/lib.rs"
+        start=$(date +%s.%N)
+        cargo build --quiet --manifest-path "$dir/Cargo.toml"
+        end=$(date +%s.%N)
+        times="$times $(awk "BEGIN { print $end - $start }")"
+    done
+    printf '%8s %11.2fs %11.2fs\n' "$kib" $times
+done
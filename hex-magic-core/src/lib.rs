@@ -0,0 +1,886 @@
+#![warn(missing_docs)]
+//! Parser of the hex strings used by the [`hex-magic`](https://docs.rs/hex-magic/) macros.
+//!
+//! This crate provides the parsing logic of `hex!` and related macros for other procedural
+//! macros. A [`HexString`] is parsed from a string literal (`"DE AD __ .."`) into a list of
+//! [`HexValue`]s, which can be written as a byte array expression or pattern with
+//! [`ToTokens`].
+//!
+//! ```
+//! use hex_magic_core::{HexString, HexValue};
+//!
+//! let hex: HexString = syn::parse_str(r#""DE AD __""#).unwrap();
+//! assert_eq!(hex.len(), 3);
+//! assert_eq!(hex.elems()[0].value(), Some(0xDE));
+//! assert!(matches!(hex.elems()[2], HexValue::Underscore { .. }));
+//! ```
+//!
+//! [`decode`] and [`encode_upper`] apply the same rules to strings at runtime, so data handled
+//! by a program is accepted exactly like the literals of its macros.
+//!
+//! Modifiers of the macros, such as `rev` or checksums, can be applied by changing the elements
+//! of a hex string and creating a new one with [`HexString::new`]. The patterns of
+//! `parse_struct!` are not part of this crate, since they describe the code reading each field
+//! rather than bytes and change with the code generation of the macros.
+
+use proc_macro2::{Literal, Span, TokenStream, TokenTree};
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use syn::parse::{Parse, ParseStream};
+
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{spanned::Spanned, LitByteStr, LitStr, Result};
+
+mod runtime;
+
+pub use runtime::{decode, decode_with, encode_lower, encode_upper, HexError};
+
+/// A single element of a hex string.
+///
+/// The span of each element is the span of the whole string literal it was parsed from.
+#[derive(Debug, Clone)]
+pub enum HexValue {
+    /// A byte with a known value.
+    Number {
+        /// The value of the byte.
+        value: u8,
+        /// The span of the literal.
+        span: Span,
+    },
+    /// A `__` wildcard matching any single byte.
+    Underscore {
+        /// The span of the literal.
+        span: Span,
+    },
+    /// A `..` wildcard matching any number of bytes.
+    DotDot {
+        /// The span of the literal.
+        span: Span,
+    },
+    /// A byte with one or both nibbles given by `?` wildcards.
+    NibbleWildcard {
+        /// The high nibble, or `None` for `?`.
+        high: Option<u8>,
+        /// The low nibble, or `None` for `?`.
+        low: Option<u8>,
+        /// The span of the literal.
+        span: Span,
+    },
+}
+
+impl fmt::Display for HexValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number { value, .. } => write!(f, "0x{:02X}", value),
+            Self::Underscore { .. } => write!(f, "_"),
+            Self::DotDot { .. } => write!(f, ".."),
+            Self::NibbleWildcard { high, low, .. } => {
+                let nibble = |n: &Option<u8>| n.map_or("?".to_string(), |n| format!("{:X}", n));
+                write!(f, "0x{}{}", nibble(high), nibble(low))
+            }
+        }
+    }
+}
+
+/// Slice patterns can only contain a single `..`, which rustc reports without pointing at the
+/// hex string.
+const MULTIPLE_DOT2: &str = "`..` can only be used once in a hex string.\n\
+                             help: try using `__` to match single bytes around the other `..`.";
+
+/// Maximum number of bytes of a hex string (4 MiB), which keeps a mistyped repeat count or an
+/// oversized file from running the compiler out of memory. Longer strings, including
+/// concatenated ones, fail to parse.
+pub const MAX_LEN: usize = 1 << 22;
+
+/// Returns the error message of a hex string of `len` bytes, more than [`MAX_LEN`].
+fn too_long(len: usize) -> String {
+    format!(
+        "hex strings can't be longer than {} bytes, found {}",
+        MAX_LEN, len
+    )
+}
+
+/// Returns the value of a hex digit or `None` for the `?` nibble wildcard.
+fn nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+impl HexValue {
+    /// Returns the byte, or `None` for wildcards.
+    pub fn value(&self) -> Option<u8> {
+        match self {
+            Self::Number { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the two uppercase hex digits of a byte.
+    pub fn to_hex(&self) -> String {
+        match self {
+            Self::Number { value, .. } => format!("{:02X}", value),
+            _ => unreachable!("wildcards are rejected before writing hex digits"),
+        }
+    }
+
+    /// Writes the value as a `u8` pattern or expression, or as an `i8` one if `signed`
+    /// (reinterpreting the byte).
+    fn to_tokens_as(&self, signed: bool, tokens: &mut TokenStream) {
+        let literal = |value: u8, span: Span| {
+            let mut lit = match signed {
+                true => Literal::i8_suffixed(value as i8),
+                false => Literal::u8_suffixed(value),
+            };
+            lit.set_span(span);
+            lit
+        };
+        match self {
+            Self::Number { value, span } => literal(*value, *span).to_tokens(tokens),
+            Self::Underscore { span } => quote_spanned!(*span=>_).to_tokens(tokens),
+            Self::DotDot { span } => quote_spanned!(*span=>..).to_tokens(tokens),
+            Self::NibbleWildcard { high, low, span } => match (high, low) {
+                // high nibble known: range of all possible low nibbles
+                (Some(high), _) => {
+                    let start = literal(high << 4, *span);
+                    let end = literal((high << 4) | 0x0F, *span);
+                    quote_spanned!(*span=>#start..=#end).to_tokens(tokens)
+                }
+                // low nibble known: alternatives for all possible high nibbles
+                (None, Some(low)) => {
+                    let values = (0..=0x0Fu8).map(|high| literal((high << 4) | low, *span));
+                    quote_spanned!(*span=>(#(| #values)*)).to_tokens(tokens)
+                }
+                (None, None) => quote_spanned!(*span=>_).to_tokens(tokens),
+            },
+        }
+    }
+}
+
+impl ToTokens for HexValue {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.to_tokens_as(false, tokens)
+    }
+}
+
+/// Bytes and wildcards parsed from a hex string literal, written as a byte array
+/// (`[0xDEu8, 0xADu8]`) or a slice pattern (`[0xDEu8, _, ..]`).
+#[derive(Debug)]
+pub struct HexString {
+    elems: Vec<HexValue>,
+    span: Span,
+}
+
+impl HexString {
+    /// Creates a hex string of the given elements, which are written with the given span,
+    /// failing if it has more than one `..` or more than [`MAX_LEN`] elements.
+    ///
+    /// ```
+    /// use hex_magic_core::{HexString, HexValue};
+    /// use proc_macro2::Span;
+    ///
+    /// let hex: HexString = syn::parse_str(r#""DE AD""#).unwrap();
+    /// let span = hex.span();
+    /// let mut elems = hex.into_elems();
+    /// elems.push(HexValue::Underscore { span });
+    /// let hex = HexString::new(elems, span).unwrap();
+    /// assert_eq!(hex.to_string(), "[0xDE, 0xAD, _]");
+    ///
+    /// let dot2 = || HexValue::DotDot { span: Span::call_site() };
+    /// assert!(HexString::new(vec![dot2(), dot2()], Span::call_site()).is_err());
+    /// ```
+    pub fn new(elems: Vec<HexValue>, span: Span) -> Result<Self> {
+        if let Some(dot2) = elems
+            .iter()
+            .filter(|elem| matches!(elem, HexValue::DotDot { .. }))
+            .nth(1)
+        {
+            return Err(syn::Error::new(dot2.span(), MULTIPLE_DOT2));
+        }
+        if elems.len() > MAX_LEN {
+            return Err(syn::Error::new(span, too_long(elems.len())));
+        }
+        Ok(Self { elems, span })
+    }
+    /// Returns the number of elements, each of which is a single byte unless it's `..`.
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+    /// Returns `true` if the hex string has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elems.is_empty()
+    }
+    /// Returns the parsed elements.
+    pub fn elems(&self) -> &[HexValue] {
+        &self.elems
+    }
+    /// Returns the parsed elements, which can be changed and turned back into a hex string with
+    /// [`HexString::new`].
+    pub fn into_elems(self) -> Vec<HexValue> {
+        self.elems
+    }
+    /// Returns the span of the string literal.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+    /// Creates a hex string of the literal bytes of a byte string.
+    pub fn from_byte_str(bstr: &LitByteStr) -> Self {
+        let span = bstr.span();
+        let elems = bstr
+            .value()
+            .into_iter()
+            .map(|value| HexValue::Number { value, span })
+            .collect();
+        Self { elems, span }
+    }
+    /// Reverses the order of the elements.
+    pub fn reverse(&mut self) {
+        self.elems.reverse();
+    }
+    /// Returns the first `..` wildcard of the hex string, if any.
+    pub fn dot2(&self) -> Option<&HexValue> {
+        self.elems
+            .iter()
+            .find(|elem| matches!(elem, HexValue::DotDot { .. }))
+    }
+    /// Writes the hex string like [`ToTokens`], but with the bytes reinterpreted as `i8` values
+    /// (`[-34i8, -83i8]`).
+    ///
+    /// ```
+    /// use hex_magic_core::HexString;
+    ///
+    /// let hex: HexString = syn::parse_str(r#""DE 01 __""#).unwrap();
+    /// assert_eq!(hex.to_i8_tokens().to_string(), "[- 34i8 , 1i8 , _]");
+    /// ```
+    pub fn to_i8_tokens(&self) -> TokenStream {
+        self.to_tokens_as(true)
+    }
+
+    fn to_tokens_as(&self, signed: bool) -> TokenStream {
+        let mut elems = TokenStream::new();
+        for (i, elem) in self.elems.iter().enumerate() {
+            if i > 0 {
+                quote!(,).to_tokens(&mut elems);
+            }
+            elem.to_tokens_as(signed, &mut elems);
+        }
+        quote_spanned!(self.span=>[#elems])
+    }
+}
+
+impl fmt::Display for HexString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}]",
+            self.elems
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Letter case required for the hex digits of a hex string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Case {
+    /// Only `A` to `F` are allowed.
+    Upper,
+    /// Only `a` to `f` are allowed.
+    Lower,
+}
+
+/// Options changing how hex strings are parsed.
+#[derive(Debug, Default)]
+pub struct HexOptions {
+    /// Letter case required for hex digits, if any.
+    pub case: Option<Case>,
+    /// Characters ignored between bytes in addition to whitespace, `:` and `-`.
+    pub separators: Vec<char>,
+    /// Number of hex digits (including wildcards) each group between separators must have.
+    pub group: Option<usize>,
+}
+
+impl HexOptions {
+    /// Sets the characters of the string literal as separators, failing on characters which
+    /// already have a meaning in hex strings.
+    pub fn set_separators(&mut self, separators: &LitStr) -> Result<()> {
+        let value = separators.value();
+        if value.is_empty() {
+            return Err(syn::Error::new(
+                separators.span(),
+                "expected at least one separator",
+            ));
+        }
+        if let Some(c) = value
+            .chars()
+            .find(|c| c.is_ascii_hexdigit() || "?_.#'[]()\\%@xX".contains(*c))
+        {
+            return Err(syn::Error::new(
+                separators.span(),
+                format!("`{}` can't be used as a separator", c),
+            ));
+        }
+        self.separators = value.chars().collect();
+        Ok(())
+    }
+
+    /// Returns `true` if the character separates bytes.
+    fn is_separator(&self, c: char) -> bool {
+        c.is_whitespace() || matches!(c, ':' | '-') || self.separators.contains(&c)
+    }
+}
+
+impl Parse for HexString {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Self::parse_with(input, &HexOptions::default())
+    }
+}
+
+impl HexString {
+    /// Parses a hex string literal with the given options.
+    pub fn parse_with(input: ParseStream, options: &HexOptions) -> Result<Self> {
+        let litstr = input.parse::<LitStr>()?;
+        let span = litstr.span();
+        let elems = parse_elems(&litstr.value(), &Source::new(&litstr), options)?;
+        Ok(Self { elems, span })
+    }
+
+    /// Parses the contents of a file, such as one embedded with `hex!(include "...")`, with the
+    /// given options. Errors point at `span` and give the line and column in the file.
+    pub fn parse_file(
+        contents: &str,
+        path: &str,
+        span: Span,
+        options: &HexOptions,
+    ) -> Result<Self> {
+        let source = Source::from_file(contents, path, span);
+        let elems = parse_elems(contents, &source, options)?;
+        Ok(Self { elems, span })
+    }
+}
+
+/// A file a hex string is read from.
+struct SourceFile {
+    path: String,
+    contents: String,
+}
+
+impl SourceFile {
+    /// Returns the line and column of the character at the given index, counted from 1.
+    fn position(&self, index: usize) -> (usize, usize) {
+        let before = &self.contents[..index];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// The literal or file a hex string is parsed from, used to point errors at the offending
+/// characters.
+#[derive(Clone)]
+struct Source {
+    literal: Option<Literal>,
+    file: Option<Rc<SourceFile>>,
+    span: Span,
+    /// Offset of the parsed characters in the source code of the literal or in the file,
+    /// known only if the literal doesn't contain escape sequences.
+    offset: Option<usize>,
+}
+
+impl Source {
+    fn new(litstr: &LitStr) -> Self {
+        let literal = match litstr.to_token_stream().into_iter().next() {
+            Some(TokenTree::Literal(literal)) => literal,
+            _ => unreachable!("string literals are single tokens"),
+        };
+        let repr = literal.to_string();
+        let value = litstr.value();
+        let offset = repr.find('"').map(|quote| quote + 1).filter(|&start| {
+            repr.get(start..start + value.len()) == Some(value.as_str())
+                && repr[start + value.len()..].starts_with('"')
+        });
+        Self {
+            literal: Some(literal),
+            file: None,
+            span: litstr.span(),
+            offset,
+        }
+    }
+
+    fn from_file(contents: &str, path: &str, span: Span) -> Self {
+        Self {
+            literal: None,
+            file: Some(Rc::new(SourceFile {
+                path: path.to_owned(),
+                contents: contents.to_owned(),
+            })),
+            span,
+            offset: Some(0),
+        }
+    }
+
+    /// Returns a source for a part of the string starting at the given index.
+    fn shifted(&self, index: usize) -> Self {
+        Self {
+            offset: self.offset.map(|offset| offset + index),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the span of the given characters, falling back to the span of the whole
+    /// literal if the compiler doesn't support spans of parts of literals.
+    fn span(&self, range: Range<usize>) -> Span {
+        self.offset
+            .zip(self.literal.as_ref())
+            .and_then(|(offset, literal)| literal.subspan(offset + range.start..offset + range.end))
+            .unwrap_or(self.span)
+    }
+
+    fn error<T: fmt::Display>(&self, range: Range<usize>, message: T) -> syn::Error {
+        match (&self.file, self.offset) {
+            (Some(file), Some(offset)) => {
+                let (line, column) = file.position(offset + range.start);
+                syn::Error::new(
+                    self.span,
+                    format!("{}\n --> {}:{}:{}", message, file.path, line, column),
+                )
+            }
+            _ => syn::Error::new(self.span(range), message),
+        }
+    }
+}
+
+/// Parses the contents of a hex string literal.
+fn parse_elems(value: &str, source: &Source, options: &HexOptions) -> Result<Vec<HexValue>> {
+    let span = source.span;
+    let mut chars = value.char_indices().peekable();
+    // most strings have two hex digits per byte
+    let mut elems: Vec<HexValue> = Vec::with_capacity(value.len() / 2);
+
+    let mut msb: Option<u8> = None;
+    let mut need_hex = false;
+    let mut need_underscore = false;
+    let mut need_dot = false;
+    let mut group_start = true;
+    let mut pending = 0;
+    // end of the last hex byte, after which a single `_` is a separator
+    let mut byte_end = None;
+    // hex digits and wildcards of the current group, starting at `group_begin`
+    let mut group_len = 0;
+    let mut group_begin = 0;
+    let check_group = |group_len: usize, range: Range<usize>| match options.group {
+        Some(expected) if group_len > 0 && group_len != expected => Err(source.error(
+            range,
+            format!(
+                "expected groups of {} hex digits, found {}",
+                expected, group_len
+            ),
+        )),
+        _ => Ok(()),
+    };
+
+    while let Some((i, c)) = chars.next() {
+        let at_group_start = group_start;
+        group_start = false;
+        let error = |message: String| Err(source.error(i..i + c.len_utf8(), message));
+        match c {
+            // insert ..
+            '.' if need_dot => {
+                need_dot = false;
+                if elems
+                    .iter()
+                    .any(|elem| matches!(elem, HexValue::DotDot { .. }))
+                {
+                    return Err(source.error(pending..i + 1, MULTIPLE_DOT2));
+                }
+                elems.push(HexValue::DotDot { span });
+            }
+            '.' => {
+                need_dot = true;
+                pending = i;
+            }
+            _ if need_dot => return error(format!("expected a second `.`, got `{}`", c)),
+
+            // insert _
+            '_' if need_underscore => {
+                need_underscore = false;
+                if group_len == 0 {
+                    group_begin = pending;
+                }
+                group_len += 2;
+                elems.push(HexValue::Underscore { span });
+            }
+            // a single `_` between hex bytes only separates them
+            '_' if byte_end == Some(i)
+                && matches!(chars.peek(), Some((_, c)) if c.is_ascii_hexdigit() || *c == '?') => {}
+            '_' => {
+                need_underscore = true;
+                pending = i;
+            }
+            _ if need_underscore => return error(format!("expected a matching `_`, got `{}`", c)),
+
+            // insert binary byte: `bin(1010_1111)`
+            'b' if !need_hex && value[i..].starts_with("bin(") => {
+                chars.nth(2);
+                let mut byte = 0u8;
+                let mut bits = 0;
+                let end = loop {
+                    match chars.next() {
+                        Some((j, ')')) => break j + 1,
+                        Some((_, c @ ('0' | '1'))) => {
+                            byte = byte.wrapping_shl(1) | (c as u8 - b'0');
+                            bits += 1;
+                        }
+                        Some((_, '_')) => {}
+                        Some((j, c)) => {
+                            return Err(source.error(
+                                j..j + c.len_utf8(),
+                                format!("expected a binary digit, got `{}`", c),
+                            ))
+                        }
+                        None => return error("expected a closing `)`".into()),
+                    }
+                };
+                if bits != 8 {
+                    return Err(source.error(
+                        i..end,
+                        format!("expected 8 binary digits in `bin(...)`, found {}", bits),
+                    ));
+                }
+                byte_end = Some(end);
+                elems.push(HexValue::Number { value: byte, span });
+            }
+
+            // insert decimal byte: `dec(255)`
+            'd' if !need_hex && value[i..].starts_with("dec(") => {
+                chars.nth(2);
+                let digits_start = i + 4;
+                let end = loop {
+                    match chars.next() {
+                        Some((j, ')')) => break j,
+                        Some((_, '0'..='9')) => {}
+                        Some((j, c)) => {
+                            return Err(source.error(
+                                j..j + c.len_utf8(),
+                                format!("expected a decimal digit, got `{}`", c),
+                            ))
+                        }
+                        None => return error("expected a closing `)`".into()),
+                    }
+                };
+                let digits = &value[digits_start..end];
+                let value = digits.parse::<u8>().map_err(|_| {
+                    source.error(
+                        i..end + 1,
+                        match digits.is_empty() {
+                            true => "expected a decimal byte in `dec(...)`".to_owned(),
+                            false => {
+                                format!("decimal byte `dec({})` is out of range (0-255)", digits)
+                            }
+                        },
+                    )
+                })?;
+                byte_end = Some(end + 1);
+                elems.push(HexValue::Number { value, span });
+            }
+
+            // check the offset of the next byte
+            '@' if at_group_start && matches!(chars.peek(), Some((_, '0'..='9'))) => {
+                let mut end = i + 1;
+                while let Some(&(j, '0'..='9')) = chars.peek() {
+                    chars.next();
+                    end = j + 1;
+                }
+                if let Some(&(j, c)) = chars.peek() {
+                    if !(options.is_separator(c) || c == '#') {
+                        return Err(source.error(
+                            j..j + c.len_utf8(),
+                            format!("expected a decimal digit, got `{}`", c),
+                        ));
+                    }
+                }
+                if elems
+                    .iter()
+                    .any(|elem| matches!(elem, HexValue::DotDot { .. }))
+                {
+                    return Err(source.error(i..end, "offsets can't be checked after `..`"));
+                }
+                let digits = &value[i + 1..end];
+                if digits.parse::<usize>().ok() != Some(elems.len()) {
+                    return Err(source.error(
+                        i..end,
+                        format!(
+                            "expected the next byte at offset {}, but it's at offset {}",
+                            digits,
+                            elems.len()
+                        ),
+                    ));
+                }
+            }
+
+            // strip 0x prefix
+            '0' if at_group_start && matches!(chars.peek(), Some((_, 'x')) | Some((_, 'X'))) => {
+                chars.next();
+            }
+            'x' | 'X' => {
+                return error("`0x` prefix is only allowed at the start of a byte group".into())
+            }
+
+            // enforce letter case
+            'a'..='f' if options.case == Some(Case::Upper) => {
+                return error(format!(
+                    "expected uppercase hex digits, got `{}` at character {}",
+                    c,
+                    value[..i].chars().count() + 1
+                ))
+            }
+            'A'..='F' if options.case == Some(Case::Lower) => {
+                return error(format!(
+                    "expected lowercase hex digits, got `{}` at character {}",
+                    c,
+                    value[..i].chars().count() + 1
+                ))
+            }
+
+            // insert hex byte or nibble wildcard
+            '0'..='9' | 'a'..='f' | 'A'..='F' | '?' if need_hex => {
+                need_hex = false;
+                group_len += 1;
+                byte_end = Some(i + 1);
+                elems.push(match (msb, nibble(c as u8)) {
+                    (Some(msb), Some(lsb)) => HexValue::Number {
+                        value: (msb << 4) | lsb,
+                        span,
+                    },
+                    (high, low) => HexValue::NibbleWildcard {
+                        high,
+                        low,
+                        span: source.span(pending..i + 1),
+                    },
+                });
+            }
+            '0'..='9' | 'a'..='f' | 'A'..='F' | '?' => {
+                need_hex = true;
+                msb = nibble(c as u8);
+                pending = i;
+                if group_len == 0 {
+                    group_begin = i;
+                }
+                group_len += 1;
+            }
+            _ if need_hex => return error(format!("expected a matching hex digit, got `{}`", c)),
+
+            // insert escaped byte: `\xDE` or `%DE`
+            '\\' | '%' => {
+                let escape = match c {
+                    '%' => "%",
+                    _ => match chars.next() {
+                        Some((_, 'x')) => "\\x",
+                        _ => return error("expected `x` after `\\`".into()),
+                    },
+                };
+                let mut byte = 0u8;
+                let mut end = i + escape.len();
+                for _ in 0..2 {
+                    match chars.next() {
+                        Some((j, c)) if c.is_ascii_hexdigit() => {
+                            let expected = match options.case {
+                                Some(Case::Upper) if c.is_ascii_lowercase() => Some("uppercase"),
+                                Some(Case::Lower) if c.is_ascii_uppercase() => Some("lowercase"),
+                                _ => None,
+                            };
+                            if let Some(expected) = expected {
+                                return Err(source.error(
+                                    j..j + 1,
+                                    format!(
+                                        "expected {} hex digits, got `{}` at character {}",
+                                        expected,
+                                        c,
+                                        value[..j].chars().count() + 1
+                                    ),
+                                ));
+                            }
+                            byte = (byte << 4) | nibble(c as u8).unwrap();
+                            end = j + 1;
+                        }
+                        Some((j, c)) => {
+                            return Err(source.error(
+                                j..j + c.len_utf8(),
+                                format!("expected two hex digits after `{}`, got `{}`", escape, c),
+                            ))
+                        }
+                        None => {
+                            return Err(source.error(
+                                i..end,
+                                format!(
+                                    "unexpected end of input: expected two hex digits after `{}`",
+                                    escape
+                                ),
+                            ))
+                        }
+                    }
+                }
+                if let Some(&(j, c)) = chars.peek() {
+                    if c.is_ascii_hexdigit() || c == '?' {
+                        return Err(source.error(
+                            i..j + 1,
+                            format!(
+                                "`{}` is followed by exactly two hex digits.\n\
+                                 help: try escaping every byte or separating bytes with spaces",
+                                escape
+                            ),
+                        ));
+                    }
+                }
+                byte_end = Some(end);
+                elems.push(HexValue::Number { value: byte, span });
+            }
+
+            // insert ASCII characters
+            '\'' => {
+                let start = elems.len();
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, c)) if c.is_ascii() => elems.push(HexValue::Number {
+                            value: c as u8,
+                            span,
+                        }),
+                        Some((j, c)) => {
+                            return Err(source.error(
+                                j..j + c.len_utf8(),
+                                format!("expected ASCII characters, got `{}`", c),
+                            ))
+                        }
+                        None => return error("expected a closing `'`".into()),
+                    }
+                }
+                if elems.len() == start {
+                    return error("empty ASCII segment".into());
+                }
+            }
+
+            // insert repeated value
+            '[' => {
+                let mut repeat = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) => break,
+                        Some((_, c)) => repeat.push(c),
+                        None => return error("expected a closing `]`".into()),
+                    }
+                }
+                let (elem, count) =
+                    parse_repeat(&repeat, elems.len(), &source.shifted(i + 1), options)?;
+                elems.extend(std::iter::repeat(elem).take(count));
+            }
+
+            // skip comments up to the end of the line
+            '#' => {
+                check_group(group_len, group_begin..i)?;
+                group_len = 0;
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                group_start = true;
+            }
+
+            // clear whitespace and separators
+            _ if options.is_separator(c) => {
+                check_group(group_len, group_begin..i)?;
+                group_len = 0;
+                group_start = true;
+            }
+
+            // fail on anything else
+            _ => return error(format!("invalid character: `{}`", c)),
+        }
+    }
+    let missing = if need_hex {
+        "incomplete byte.\nhelp: bytes are written as two hex digits, e.g. `0A`"
+    } else if need_underscore {
+        "expected a matching `_`"
+    } else if need_dot {
+        "expected a second `.`"
+    } else {
+        check_group(group_len, group_begin..value.len())?;
+        if elems.len() > MAX_LEN {
+            return Err(source.error(0..value.len(), too_long(elems.len())));
+        }
+        return Ok(elems);
+    };
+    Err(source.error(
+        pending..value.len(),
+        format!("unexpected end of input: {}", missing),
+    ))
+}
+
+/// Parses the inside of a `[BYTE; COUNT]` repetition following `len` bytes.
+fn parse_repeat(
+    repeat: &str,
+    len: usize,
+    source: &Source,
+    options: &HexOptions,
+) -> Result<(HexValue, usize)> {
+    let (elem, count) = match repeat.split_once(';') {
+        Some(split) => split,
+        None => {
+            return Err(source.error(
+                0..repeat.len(),
+                format!("expected `[BYTE; COUNT]`, got `[{}]`", repeat),
+            ))
+        }
+    };
+    let mut elems = parse_elems(elem, source, options)?;
+    let elem = match elems.pop() {
+        Some(HexValue::DotDot { .. }) => {
+            return Err(source.error(0..elem.len(), "`..` can't be repeated"));
+        }
+        Some(elem) if elems.is_empty() => elem,
+        _ => {
+            return Err(source.error(
+                0..elem.len(),
+                format!("expected a single byte to repeat, got `{}`", elem.trim()),
+            ))
+        }
+    };
+    let count_start = repeat.len() - count.len();
+    let digits = count.trim();
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(source.error(
+            count_start..repeat.len(),
+            format!("expected a decimal repeat count, got `{}`", digits),
+        ));
+    }
+    // counts too large for `usize` are too large for a hex string as well
+    match digits.parse::<usize>().ok().filter(|count| {
+        len.checked_add(*count)
+            .map_or(false, |total| total <= MAX_LEN)
+    }) {
+        Some(count) => Ok((elem, count)),
+        None => Err(source.error(
+            count_start..repeat.len(),
+            format!(
+                "repeat count `{}` exceeds the maximum length of a hex string ({} bytes)",
+                digits, MAX_LEN
+            ),
+        )),
+    }
+}
+
+impl ToTokens for HexString {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.to_tokens_as(false).to_tokens(tokens)
+    }
+}
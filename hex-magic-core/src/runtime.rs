@@ -0,0 +1,104 @@
+//! Encoding and decoding of hex strings at runtime with the rules of the macros.
+
+use std::fmt;
+
+use proc_macro2::Span;
+use syn::LitStr;
+
+use crate::{parse_elems, HexOptions, HexValue, Source};
+
+/// Error of [`decode`], with the same message the macros would report at compile time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexError {
+    message: String,
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HexError {}
+
+impl From<syn::Error> for HexError {
+    fn from(error: syn::Error) -> Self {
+        Self {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Decodes a hex string following the rules of `hex!`, so runtime strings accept exactly what
+/// the macros accept: digits must come in pairs, the same separators, comments, ASCII strings
+/// and escapes are allowed, and wildcards are rejected. Strings decoding to more than
+/// [`MAX_LEN`](crate::MAX_LEN) bytes are rejected as well, so an untrusted repeat count can't
+/// exhaust the memory of the program.
+///
+/// ```
+/// use hex_magic_core::decode;
+///
+/// assert_eq!(decode("DE:AD be-ef # comment").unwrap(), [0xDE, 0xAD, 0xBE, 0xEF]);
+/// assert_eq!(decode("'Hi' 00").unwrap(), b"Hi\0");
+/// assert!(decode("DEA").is_err());
+/// assert!(decode("DE __").is_err());
+/// assert_eq!(decode("0B0C bin(0000_0001)").unwrap(), [0x0B, 0x0C, 0x01]);
+/// assert_eq!(decode("0b00").unwrap(), [0x0B, 0x00]);
+/// assert_eq!(decode("dec(10) d0cf11e0").unwrap(), [0x0A, 0xD0, 0xCF, 0x11, 0xE0]);
+/// assert_eq!(decode("d1 d2").unwrap(), [0xD1, 0xD2]);
+/// assert!(decode("[00; 99999999999]").is_err());
+/// ```
+pub fn decode(s: &str) -> Result<Vec<u8>, HexError> {
+    decode_with(s, &HexOptions::default())
+}
+
+/// Decodes a hex string with the given options, like `hex!(upper, sep = "/", ...)`.
+///
+/// ```
+/// use hex_magic_core::{decode_with, Case, HexOptions};
+///
+/// let options = HexOptions {
+///     case: Some(Case::Upper),
+///     separators: vec!['/'],
+///     ..HexOptions::default()
+/// };
+/// assert_eq!(decode_with("DE/AD", &options).unwrap(), [0xDE, 0xAD]);
+/// assert!(decode_with("de/ad", &options).is_err());
+/// ```
+pub fn decode_with(s: &str, options: &HexOptions) -> Result<Vec<u8>, HexError> {
+    let litstr = LitStr::new(s, Span::call_site());
+    parse_elems(s, &Source::new(&litstr), options)?
+        .into_iter()
+        .map(|elem| match elem {
+            HexValue::Number { value, .. } => Ok(value),
+            elem => Err(HexError {
+                message: format!(
+                    "wildcards are not allowed in decoded hex strings, found `{}`",
+                    elem
+                ),
+            }),
+        })
+        .collect()
+}
+
+/// Encodes bytes as uppercase hex digits without separators, like `hex_str!`.
+///
+/// ```
+/// use hex_magic_core::encode_upper;
+///
+/// assert_eq!(encode_upper(&[0xDE, 0xAD, 0x01]), "DEAD01");
+/// ```
+pub fn encode_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Encodes bytes as lowercase hex digits without separators.
+///
+/// ```
+/// use hex_magic_core::encode_lower;
+///
+/// assert_eq!(encode_lower(&[0xDE, 0xAD, 0x01]), "dead01");
+/// ```
+pub fn encode_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,189 @@
+use proc_macro2::{Delimiter, Group, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use syn::{
+    punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute, Data, DeriveInput, Fields,
+    Generics, Ident, LitInt, Result,
+};
+
+use crate::parse_struct::{FieldType, HexStruct};
+
+/// Options of fields given by `#[hex(...)]`, which are passed on to `parse_struct!` as
+/// attributes except for `skip(N)`.
+const FIELD_OPTIONS: &[&str] = &["le", "be", "note", "order", "bom", "error_kind", "skip"];
+
+/// Options of the struct given by `#[hex(...)]`, which apply to all of its fields.
+const STRUCT_OPTIONS: &[&str] = &["endian", "error_kind"];
+
+/// An option given by `#[hex(...)]` instead of a byte pattern, such as `le` or `skip(4)`.
+#[derive(Debug)]
+struct HexOption {
+    name: Ident,
+    args: Option<Group>,
+}
+
+impl HexOption {
+    /// Parses the options of a `#[hex(...)]` attribute, or returns `None` if it doesn't only
+    /// contain the given options and is a byte pattern.
+    fn parse_all(attr: &Attribute, names: &[&str]) -> Option<Vec<Self>> {
+        let options = attr
+            .parse_args_with(Punctuated::<Self, Comma>::parse_terminated)
+            .ok()?;
+        match !options.is_empty()
+            && options
+                .iter()
+                .all(|option| names.iter().any(|name| option.name == name))
+        {
+            true => Some(options.into_iter().collect()),
+            false => None,
+        }
+    }
+}
+
+impl Parse for HexOption {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        let args = match input.cursor().group(Delimiter::Parenthesis) {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
+        Ok(Self { name, args })
+    }
+}
+
+impl ToTokens for HexOption {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let HexOption { name, args } = self;
+        quote!(#[#name #args]).to_tokens(tokens)
+    }
+}
+
+/// Input of the `ParseStruct` derive macro: a struct with named fields, the bytes of which are
+/// given by `#[hex(BYTE_PATTERN)]` attributes.
+#[derive(Debug)]
+pub struct DeriveParseStruct {
+    ident: Ident,
+    generics: Generics,
+    /// `parse_struct!` input built from the fields.
+    hex_struct: HexStruct,
+}
+
+impl Parse for DeriveParseStruct {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let input: DeriveInput = input.parse()?;
+        let fields = match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => Some(&fields.named),
+                _ => None,
+            },
+            _ => None,
+        }
+        .ok_or_else(|| {
+            syn::Error::new(
+                input.ident.span(),
+                "`ParseStruct` can only be derived for structs with named fields",
+            )
+        })?;
+
+        let mut schema = TokenStream::new();
+        for field in fields {
+            let ident = &field.ident;
+            let mut attrs = vec![];
+            let mut pattern: Option<TokenStream> = None;
+            let mut bom = false;
+            for attr in &field.attrs {
+                if attr.path.is_ident("doc") {
+                    attrs.push(attr.to_token_stream());
+                } else if !attr.path.is_ident("hex") {
+                    continue;
+                } else if let Some(options) = HexOption::parse_all(attr, FIELD_OPTIONS) {
+                    for option in options {
+                        bom |= option.name == "bom";
+                        if option.name != "skip" {
+                            attrs.push(option.to_token_stream());
+                            continue;
+                        }
+                        // skipped bytes come before the field
+                        let count: LitInt = match &option.args {
+                            Some(args) => syn::parse2(args.stream())?,
+                            None => {
+                                return Err(syn::Error::new(
+                                    option.name.span(),
+                                    "expected the number of bytes to skip: `skip(N)`",
+                                ))
+                            }
+                        };
+                        quote!(_: skip(#count),).to_tokens(&mut schema);
+                    }
+                } else if pattern.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "duplicate byte pattern in `#[hex(...)]` attributes",
+                    ));
+                } else {
+                    pattern = Some(attr.parse_args()?);
+                }
+            }
+            let pattern = pattern.ok_or_else(|| {
+                syn::Error::new(
+                    ident.span(),
+                    "expected a `#[hex(...)]` attribute giving the bytes of the field",
+                )
+            })?;
+
+            let ty = &field.ty;
+            // byte order marks are plain bytes
+            match FieldType::convertible(ty).filter(|_| !bom) {
+                Some(true) => quote!(#(#attrs)* #ident: #ty @? #pattern,),
+                Some(false) => quote!(#(#attrs)* #ident: #ty @ #pattern,),
+                None => quote!(#(#attrs)* #ident: #pattern,),
+            }
+            .to_tokens(&mut schema);
+        }
+
+        // the default byte order and error kind apply to the whole struct
+        let mut struct_attrs = vec![];
+        for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("hex")) {
+            let options = HexOption::parse_all(attr, STRUCT_OPTIONS).ok_or_else(|| {
+                syn::Error::new_spanned(
+                    attr,
+                    "expected `#[hex(endian(le|be))]` or `#[hex(error_kind(KIND))]` on the struct",
+                )
+            })?;
+            struct_attrs.extend(options);
+        }
+        let hex_struct = syn::parse2(quote!(reader => #(#struct_attrs)* Self { #schema }))?;
+
+        Ok(Self {
+            ident: input.ident,
+            generics: input.generics,
+            hex_struct,
+        })
+    }
+}
+
+impl ToTokens for DeriveParseStruct {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let DeriveParseStruct {
+            ident,
+            generics,
+            hex_struct,
+        } = self;
+        let reader = match hex_struct.is_delimited() {
+            true => quote!(::std::io::BufRead),
+            false => quote!(::std::io::Read),
+        };
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        quote!(
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// Parses the struct from the reader as described by the `#[hex(...)]`
+                /// attributes of its fields.
+                pub fn parse<R: #reader>(reader: R) -> ::std::io::Result<Self> {
+                    #hex_struct
+                }
+            }
+        )
+        .to_tokens(tokens)
+    }
+}
@@ -0,0 +1,93 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote_spanned, ToTokens};
+use syn::parse::ParseStream;
+use syn::{spanned::Spanned, Result};
+
+use super::{HexString, HexStringExt, HexValue};
+
+/// Values computed from the bytes of a hex string at compile time.
+#[derive(Debug, Clone, Copy)]
+pub enum Digest {
+    /// The bytes along with their number.
+    WithLen,
+    /// The number of bytes.
+    Len,
+    /// The CRC-32 checksum of the bytes.
+    Crc32,
+    /// The bytes written as uppercase hex digits.
+    Str,
+}
+
+/// Input of the `hex_len!`, `hex_with_len!`, `hex_crc32!` and `hex_str!` macros: a hex string
+/// without wildcards (other than single-byte wildcards for `hex_len!`).
+#[derive(Debug)]
+pub struct HexDigest {
+    digest: Digest,
+    hex: HexString,
+}
+
+impl HexDigest {
+    pub fn parse_as(input: ParseStream, digest: Digest) -> Result<Self> {
+        let hex: HexString = input.parse()?;
+        if let (Digest::Len, Some(dot2)) = (digest, hex.dot2()) {
+            // other wildcards still stand for a single byte
+            return Err(syn::Error::new(
+                dot2.span(),
+                "the length of a hex string containing `..` isn't known",
+            ));
+        }
+        hex.ensure_bytes(match digest {
+            Digest::Len => return Ok(Self { digest, hex }),
+            Digest::WithLen => "`hex_with_len!`",
+            Digest::Crc32 => "a checksum",
+            Digest::Str => "a string",
+        })?;
+        Ok(Self { digest, hex })
+    }
+}
+
+/// Computes the CRC-32 (IEEE 802.3, as used by zlib and PNG) of the bytes.
+fn crc32<'a, I: Iterator<Item = &'a HexValue>>(elems: I) -> u32 {
+    let mut crc = !0u32;
+    for elem in elems {
+        let value = match elem {
+            HexValue::Number { value, .. } => *value,
+            _ => unreachable!("wildcards are rejected before computing checksums"),
+        };
+        crc ^= u32::from(value);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (!(crc & 1)).wrapping_add(1));
+        }
+    }
+    !crc
+}
+
+impl ToTokens for HexDigest {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let span = self.hex.span();
+        match self.digest {
+            Digest::WithLen => {
+                let hex = &self.hex;
+                let mut len = Literal::usize_suffixed(hex.len());
+                len.set_span(span);
+                quote_spanned!(span=>(#hex, #len)).to_tokens(tokens)
+            }
+            Digest::Len => {
+                let mut len = Literal::usize_suffixed(self.hex.len());
+                len.set_span(span);
+                len.to_tokens(tokens)
+            }
+            Digest::Crc32 => {
+                let mut crc = Literal::u32_suffixed(crc32(self.hex.elems().iter()));
+                crc.set_span(span);
+                crc.to_tokens(tokens)
+            }
+            Digest::Str => {
+                let string: String = self.hex.elems().iter().map(HexValue::to_hex).collect();
+                let mut string = Literal::string(&string);
+                string.set_span(span);
+                string.to_tokens(tokens)
+            }
+        }
+    }
+}
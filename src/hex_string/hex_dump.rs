@@ -0,0 +1,52 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Result, Token};
+
+/// Number of bytes on each line of a hexdump.
+const LINE_LEN: usize = 16;
+
+/// Input of the `hexdump!` macro: an expression giving the bytes to format.
+#[derive(Debug)]
+pub struct HexDump {
+    bytes: Expr,
+}
+
+impl Parse for HexDump {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let bytes = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self { bytes })
+    }
+}
+
+impl ToTokens for HexDump {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let bytes = &self.bytes;
+        // `offset  hex bytes  |ascii|` lines, with the hex column padded on the last line
+        quote!({
+            use ::core::fmt::Write as _;
+            let bytes: &[u8] = ::core::convert::AsRef::<[u8]>::as_ref(&(#bytes));
+            let mut dump = ::std::string::String::new();
+            for (line, chunk) in bytes.chunks(#LINE_LEN).enumerate() {
+                let _ = ::core::write!(dump, "{:08X} ", line * #LINE_LEN);
+                for i in 0..#LINE_LEN {
+                    match chunk.get(i) {
+                        Some(byte) => {
+                            let _ = ::core::write!(dump, " {:02X}", byte);
+                        }
+                        None => dump.push_str("   "),
+                    }
+                }
+                dump.push_str("  |");
+                dump.extend(chunk.iter().map(|&byte| match byte {
+                    b' '..=b'~' => byte as char,
+                    _ => '.',
+                }));
+                dump.push_str("|\n");
+            }
+            dump
+        })
+        .to_tokens(tokens)
+    }
+}
@@ -0,0 +1,499 @@
+use proc_macro2::Span;
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, ToTokens};
+use std::path::PathBuf;
+use std::{env, fs};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    parenthesized, token::Paren, Expr, LitByteStr, LitInt, LitStr, Path, Result, Token, Type,
+};
+
+use super::{Case, HexOptions, HexString, HexStringExt, HexValue};
+
+mod kw {
+    syn::custom_keyword!(slice);
+    syn::custom_keyword!(rev);
+    syn::custom_keyword!(nibble_swap);
+    syn::custom_keyword!(upper);
+    syn::custom_keyword!(lower);
+    syn::custom_keyword!(with_xor_checksum);
+    syn::custom_keyword!(with_sum_checksum);
+    syn::custom_keyword!(fill);
+    syn::custom_keyword!(sep);
+    syn::custom_keyword!(group);
+    syn::custom_keyword!(byte_str);
+    syn::custom_keyword!(include);
+}
+
+/// Checksum byte appended to the bytes of the `hex!` macro.
+#[derive(Debug, Clone, Copy)]
+enum Checksum {
+    /// All bytes XORed together.
+    Xor,
+    /// The wrapping sum of all bytes.
+    Sum,
+}
+
+impl Checksum {
+    fn compute(self, bytes: impl Iterator<Item = u8>) -> u8 {
+        match self {
+            Self::Xor => bytes.fold(0, |acc, byte| acc ^ byte),
+            Self::Sum => bytes.fold(0, u8::wrapping_add),
+        }
+    }
+}
+
+/// A single argument of the `hex!` macro.
+#[derive(Debug)]
+enum HexPart {
+    /// Consecutive hex strings and byte strings.
+    Hex(HexString),
+    /// A constant byte array which is copied into the output.
+    Const(Path),
+    /// A byte repeated a number of times given by a constant expression: `fill(BYTE, COUNT)`.
+    Fill { byte: Box<Expr>, count: Box<Expr> },
+}
+
+impl ToTokens for HexPart {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Self::Hex(hex) => hex.to_tokens(tokens),
+            Self::Const(path) => path.to_tokens(tokens),
+            Self::Fill { byte, count } => quote!([{
+                let byte: u8 = #byte;
+                byte
+            }; #count])
+            .to_tokens(tokens),
+        }
+    }
+}
+
+/// Input of the `hex!` macro: hex strings, byte strings and constants preceded by optional
+/// modifiers.
+#[derive(Debug)]
+pub struct HexInput {
+    slice: Option<kw::slice>,
+    /// Hex strings are written as byte string literals instead of arrays.
+    byte_str: Option<kw::byte_str>,
+    /// 8-bit integer type the bytes are cast to, given by `as TYPE`.
+    cast: Option<Type>,
+    /// The bytes of a single hex string are written as `i8` literals, given by `as i8`.
+    signed: bool,
+    /// Type of the array, such as an alias of `[u8; N]`, given before the first string.
+    ty: Option<Path>,
+    parts: Vec<HexPart>,
+    len: Option<usize>,
+    /// Absolute paths of the files read by `include "PATH"`.
+    included: Vec<PathBuf>,
+}
+
+impl Parse for HexInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut slice: Option<kw::slice> = None;
+        let mut byte_str: Option<kw::byte_str> = None;
+        let mut rev: Option<kw::rev> = None;
+        let mut nibble_swap: Option<kw::nibble_swap> = None;
+        let mut options = HexOptions::default();
+        let mut cast: Option<Type> = None;
+        let mut checksum: Option<(Checksum, Span)> = None;
+        loop {
+            if input.peek(kw::slice) {
+                slice = Some(input.parse()?);
+            } else if input.peek(kw::byte_str) {
+                byte_str = Some(input.parse()?);
+            } else if input.peek(kw::rev) {
+                rev = Some(input.parse()?);
+            } else if input.peek(kw::nibble_swap) {
+                nibble_swap = Some(input.parse()?);
+            } else if input.peek(kw::upper) {
+                input.parse::<kw::upper>()?;
+                options.case = Some(Case::Upper);
+            } else if input.peek(kw::lower) {
+                input.parse::<kw::lower>()?;
+                options.case = Some(Case::Lower);
+            } else if input.peek(Token![as]) {
+                input.parse::<Token![as]>()?;
+                let ty = input.parse()?;
+                cast = cast_type(&ty)?.then_some(ty);
+            } else if input.peek(kw::with_xor_checksum) {
+                let kw = input.parse::<kw::with_xor_checksum>()?;
+                checksum = Some((Checksum::Xor, kw.span));
+            } else if input.peek(kw::with_sum_checksum) {
+                let kw = input.parse::<kw::with_sum_checksum>()?;
+                checksum = Some((Checksum::Sum, kw.span));
+            } else if input.peek(kw::sep) && input.peek2(Token![=]) {
+                input.parse::<kw::sep>()?;
+                input.parse::<Token![=]>()?;
+                options.set_separators(&input.parse()?)?;
+                input.parse::<Token![,]>()?;
+            } else if input.peek(kw::group) && input.peek2(Token![=]) {
+                input.parse::<kw::group>()?;
+                input.parse::<Token![=]>()?;
+                let group: LitInt = input.parse()?;
+                match group.base10_parse::<usize>()? {
+                    0 => {
+                        return Err(syn::Error::new(
+                            group.span(),
+                            "group length must be greater than 0",
+                        ))
+                    }
+                    len => options.group = Some(len),
+                }
+                input.parse::<Token![,]>()?;
+            } else {
+                break;
+            }
+        }
+
+        // a type is followed by a string, unlike constants, which are followed by a comma
+        let fork = input.fork();
+        let ty: Option<Path> = match !input.peek(kw::include)
+            && fork.parse::<Path>().is_ok()
+            && (fork.peek(LitStr) || fork.peek(LitByteStr))
+        {
+            true => Some(input.parse()?),
+            false => None,
+        };
+        if let (Some(_), Some(cast)) = (&byte_str, &cast) {
+            return Err(syn::Error::new_spanned(
+                cast,
+                "byte strings can't be cast.\n\
+                 help: remove `byte_str` to cast the bytes of an array",
+            ));
+        }
+        if let (Some(slice), Some(_)) = (&slice, &ty) {
+            return Err(syn::Error::new(
+                slice.span,
+                "slices can't have a type.\n\
+                 help: remove `slice` to assign the array to the type",
+            ));
+        }
+
+        // concatenated hex strings and constants followed by an optional expected length
+        let mut parts: Vec<HexPart> = vec![];
+        let mut len: Option<LitInt> = None;
+        let mut included = vec![];
+        loop {
+            if input.peek(LitStr) || input.peek(LitByteStr) {
+                let hex = match input.parse::<Option<LitByteStr>>()? {
+                    Some(bstr) => HexString::from_byte_str(&bstr),
+                    None => HexString::parse_with(input, &options)?,
+                };
+                match parts.last_mut() {
+                    Some(HexPart::Hex(last)) => last.extend(hex)?,
+                    _ => parts.push(HexPart::Hex(hex)),
+                }
+            } else if input.peek(kw::include) && input.peek2(LitStr) {
+                input.parse::<kw::include>()?;
+                let (hex, path) = include(&input.parse()?, &options)?;
+                included.push(path);
+                match parts.last_mut() {
+                    Some(HexPart::Hex(last)) => last.extend(hex)?,
+                    _ => parts.push(HexPart::Hex(hex)),
+                }
+            } else if input.peek(kw::fill) && input.peek2(Paren) {
+                input.parse::<kw::fill>()?;
+                let content;
+                parenthesized!(content in input);
+                let byte = Box::new(content.parse()?);
+                content.parse::<Token![,]>()?;
+                let count = Box::new(content.parse()?);
+                content.parse::<Option<Token![,]>>()?;
+                parts.push(HexPart::Fill { byte, count });
+            } else if !parts.is_empty() && input.peek(LitInt) {
+                len = Some(input.parse()?);
+                input.parse::<Option<Token![,]>>()?;
+                break;
+            } else {
+                parts.push(HexPart::Const(input.parse().map_err(|_| {
+                    input.error(
+                        "expected a hex string, a byte string, a constant byte array, \
+                         `include \"PATH\"` or `fill(BYTE, COUNT)`",
+                    )
+                })?));
+            }
+            if input.parse::<Option<Token![,]>>()?.is_none() || input.is_empty() {
+                break;
+            }
+        }
+
+        let mut signed = false;
+        match parts.as_slice() {
+            [HexPart::Hex(_)] if is_i8(&cast) => {
+                // reinterpreted bytes can still be used as match patterns
+                signed = true;
+                cast = None;
+            }
+            parts => {
+                if cast.is_some() {
+                    for part in parts {
+                        if let HexPart::Hex(hex) = part {
+                            hex.ensure_bytes("bytes cast with `as`")?;
+                        }
+                    }
+                }
+            }
+        }
+
+        match parts.as_mut_slice() {
+            [HexPart::Hex(hex)] => {
+                if slice.is_some() {
+                    hex.ensure_bytes("a slice")?;
+                }
+                if let Some(rev) = rev {
+                    if hex.dot2().is_some() {
+                        return Err(syn::Error::new(
+                            rev.span,
+                            "hex strings containing `..` can't be reversed",
+                        ));
+                    }
+                    hex.reverse();
+                }
+                if nibble_swap.is_some() {
+                    hex.swap_nibbles()?;
+                }
+                if let Some((checksum, _)) = checksum {
+                    hex.ensure_bytes("a checksum")?;
+                    let bytes = hex.elems().iter().filter_map(HexValue::value);
+                    hex.push(checksum.compute(bytes))?;
+                }
+                if let Some(len) = &len {
+                    let expected = len.base10_parse::<usize>()?;
+                    if hex.dot2().is_some() {
+                        return Err(syn::Error::new(
+                            len.span(),
+                            "the length of a hex string containing `..` can't be checked",
+                        ));
+                    }
+                    if hex.len() != expected {
+                        return Err(syn::Error::new(
+                            hex.span(),
+                            format!("expected {} bytes, found {}", expected, hex.len()),
+                        ));
+                    }
+                }
+            }
+            parts => {
+                if let Some(rev) = rev {
+                    return Err(syn::Error::new(
+                        rev.span,
+                        "interpolated constants can't be reversed",
+                    ));
+                }
+                if let Some(nibble_swap) = nibble_swap {
+                    return Err(syn::Error::new(
+                        nibble_swap.span,
+                        "nibbles of interpolated constants can't be swapped",
+                    ));
+                }
+                if let Some((_, span)) = checksum {
+                    return Err(syn::Error::new(
+                        span,
+                        "checksums can't be computed over interpolated constants",
+                    ));
+                }
+                for part in parts {
+                    if let HexPart::Hex(hex) = part {
+                        hex.ensure_bytes("hex strings with interpolated constants")?;
+                    }
+                }
+            }
+        }
+
+        let target = match (&byte_str, &ty) {
+            (Some(_), _) => Some("byte strings"),
+            (None, Some(_)) => Some("typed byte arrays"),
+            (None, None) => None,
+        };
+        if let Some(target) = target {
+            for part in parts.iter() {
+                if let HexPart::Hex(hex) = part {
+                    hex.ensure_bytes(target)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            slice,
+            byte_str,
+            cast,
+            signed,
+            ty,
+            parts,
+            len: len.map(|len| len.base10_parse()).transpose()?,
+            included,
+        })
+    }
+}
+
+impl ToTokens for HexInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let array = match self.parts.as_slice() {
+            [HexPart::Hex(hex)] => self.hex_tokens(hex),
+            parts => {
+                // lengths of constants are only known after expansion,
+                // so the array is built by a const-evaluable block
+                let lens = parts.iter().map(|part| match part {
+                    HexPart::Hex(hex) => {
+                        let len = hex.len();
+                        quote!(#len)
+                    }
+                    HexPart::Const(path) => quote!(#path.len()),
+                    HexPart::Fill { count, .. } => quote!((#count)),
+                });
+                let len_check = self.len.map(|len| {
+                    quote!(
+                        const _: () = ::core::assert!(LEN == #len, "unexpected number of bytes");
+                    )
+                });
+                let copies = parts.iter().map(|part| {
+                    let part = match part {
+                        HexPart::Hex(hex) => self.hex_tokens(hex),
+                        part => quote!(#part),
+                    };
+                    quote!(
+                        let part: &[u8] = &#part;
+                        let mut j = 0;
+                        while j < part.len() {
+                            bytes[i] = part[j];
+                            i += 1;
+                            j += 1;
+                        }
+                    )
+                });
+                quote!({
+                    const LEN: usize = 0 #(+ #lens)*;
+                    #len_check
+                    const BYTES: [u8; LEN] = {
+                        let mut bytes = [0u8; LEN];
+                        let mut i = 0;
+                        #(#copies)*
+                        bytes
+                    };
+                    BYTES
+                })
+            }
+        };
+
+        let (array, elem_type) = match &self.cast {
+            Some(ty) => (
+                quote!({
+                    const BYTES: &[u8] = &#array;
+                    const CAST: [#ty; BYTES.len()] = {
+                        let mut cast = [0; BYTES.len()];
+                        let mut i = 0;
+                        while i < BYTES.len() {
+                            cast[i] = BYTES[i] as #ty;
+                            i += 1;
+                        }
+                        cast
+                    };
+                    CAST
+                }),
+                quote!(#ty),
+            ),
+            None if self.signed => (array, quote!(i8)),
+            None => (array, quote!(u8)),
+        };
+
+        let output = if self.slice.is_some() {
+            quote!({
+                const SLICE: &[#elem_type] = &#array;
+                SLICE
+            })
+        } else if let Some(ty) = &self.ty {
+            // arrays of the wrong length are reported by the type system
+            quote!({
+                const TYPED: #ty = #array;
+                TYPED
+            })
+        } else {
+            array
+        };
+
+        // `include_bytes!` makes the compiler rebuild the crate when an included file changes,
+        // at the cost of the output no longer being a pattern
+        if self.included.is_empty() {
+            return output.to_tokens(tokens);
+        }
+        let paths = self
+            .included
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned());
+        quote!({
+            #(const _: &[u8] = ::core::include_bytes!(#paths);)*
+            #output
+        })
+        .to_tokens(tokens)
+    }
+}
+
+impl HexInput {
+    /// Fails if any hex string contains wildcards, for inputs only used as values.
+    pub fn ensure_bytes(&self, target: &str) -> Result<()> {
+        self.parts.iter().try_for_each(|part| match part {
+            HexPart::Hex(hex) => hex.ensure_bytes(target),
+            HexPart::Const(_) | HexPart::Fill { .. } => Ok(()),
+        })
+    }
+
+    /// Returns the bytes of a hex string as an array literal, or as a dereferenced byte string
+    /// literal with `byte_str`, which is a single token and faster to compile for large strings.
+    fn hex_tokens(&self, hex: &HexString) -> TokenStream {
+        match (&self.byte_str, self.signed) {
+            (Some(_), _) => {}
+            (None, true) => return hex.to_i8_tokens(),
+            (None, false) => return quote!(#hex),
+        }
+        let bytes: Vec<u8> = hex.elems().iter().filter_map(HexValue::value).collect();
+        let bstr = Literal::byte_string(&bytes);
+        quote!(*#bstr)
+    }
+}
+
+/// Returns `true` if the type is `i8`.
+fn is_i8(ty: &Option<Type>) -> bool {
+    matches!(ty, Some(Type::Path(path)) if path.path.is_ident("i8"))
+}
+
+/// Checks that bytes can be cast to the type, returning `false` if it's `u8` and no cast is
+/// needed.
+fn cast_type(ty: &Type) -> Result<bool> {
+    let ident = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last().map(|s| &s.ident),
+        _ => None,
+    };
+    match ident.map(|ident| ident.to_string()).as_deref() {
+        Some("u8") => Ok(false),
+        Some("i8" | "c_char" | "c_schar" | "c_uchar") => Ok(true),
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "expected an 8-bit integer type (`i8`, `u8` or `c_char`)",
+        )),
+    }
+}
+
+/// Reads and parses the hex string in the file at the given path, relative to the root of the
+/// crate calling the macro, along with the absolute path of the file.
+fn include(path: &LitStr, options: &HexOptions) -> Result<(HexString, PathBuf)> {
+    let relative = path.value();
+    let dir = env::var_os("CARGO_MANIFEST_DIR").ok_or_else(|| {
+        syn::Error::new(
+            path.span(),
+            "`include` is relative to `CARGO_MANIFEST_DIR`, which is only set by Cargo",
+        )
+    })?;
+    let error = |error: std::io::Error| {
+        syn::Error::new(
+            path.span(),
+            format!("couldn't read `{}`: {}", relative, error),
+        )
+    };
+    let absolute = PathBuf::from(dir)
+        .join(&relative)
+        .canonicalize()
+        .map_err(error)?;
+    let contents = fs::read_to_string(&absolute).map_err(error)?;
+    let hex = HexString::parse_file(&contents, &relative, path.span(), options)?;
+    Ok((hex, absolute))
+}
@@ -0,0 +1,178 @@
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Result, Token};
+
+use super::{HexString, HexStringExt, HexValue};
+
+mod kw {
+    syn::custom_keyword!(le);
+    syn::custom_keyword!(be);
+}
+
+/// Unsigned integer types which can be created from hex strings.
+#[derive(Debug, Clone, Copy)]
+pub enum IntType {
+    U16,
+    U32,
+    U64,
+    U128,
+}
+
+impl IntType {
+    pub fn size(self) -> usize {
+        match self {
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 => 8,
+            Self::U128 => 16,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+        }
+    }
+    pub fn literal(self, value: u128) -> Literal {
+        match self {
+            Self::U16 => Literal::u16_suffixed(value as u16),
+            Self::U32 => Literal::u32_suffixed(value as u32),
+            Self::U64 => Literal::u64_suffixed(value as u64),
+            Self::U128 => Literal::u128_suffixed(value),
+        }
+    }
+}
+
+/// Folds bytes into an unsigned integer.
+pub fn fold_bytes<'a, I: DoubleEndedIterator<Item = &'a HexValue>>(
+    elems: I,
+    little_endian: bool,
+) -> u128 {
+    let fold = |acc: u128, elem: &HexValue| match elem {
+        HexValue::Number { value, .. } => (acc << 8) | u128::from(*value),
+        _ => unreachable!("wildcards are rejected before folding"),
+    };
+    if little_endian {
+        elems.rev().fold(0, fold)
+    } else {
+        elems.fold(0, fold)
+    }
+}
+
+/// Input of the `hex_u16!`, `hex_u32!`, `hex_u64!` and `hex_u128!` macros:
+/// a hex string with an optional `le` or `be` byte order.
+#[derive(Debug)]
+pub struct HexInt {
+    ty: IntType,
+    value: u128,
+    span: Span,
+}
+
+/// Parses an optional `le` or `be` byte order, returning `true` for `le`.
+fn parse_little_endian(input: ParseStream) -> Result<bool> {
+    if input.peek(kw::le) {
+        input.parse::<kw::le>()?;
+        Ok(true)
+    } else {
+        input.parse::<Option<kw::be>>()?;
+        Ok(false)
+    }
+}
+
+impl HexInt {
+    pub fn parse_as(input: ParseStream, ty: IntType) -> Result<Self> {
+        let little_endian = parse_little_endian(input)?;
+
+        let hex: HexString = input.parse()?;
+        hex.ensure_bytes(&format!("a `{}`", ty.name()))?;
+        if hex.len() != ty.size() {
+            return Err(syn::Error::new(
+                hex.span(),
+                format!(
+                    "expected {} bytes for a `{}`, found {}",
+                    ty.size(),
+                    ty.name(),
+                    hex.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            ty,
+            value: fold_bytes(hex.elems().iter(), little_endian),
+            span: hex.span(),
+        })
+    }
+}
+
+impl ToTokens for HexInt {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let mut lit = self.ty.literal(self.value);
+        lit.set_span(self.span);
+        lit.to_tokens(tokens);
+    }
+}
+
+/// Input of the `hex_limbs!` macro: a limb type followed by a hex string with an optional `le`
+/// or `be` byte order, which is split into limbs of that type.
+#[derive(Debug)]
+pub struct HexLimbs {
+    ty: IntType,
+    values: Vec<u128>,
+    span: Span,
+}
+
+impl Parse for HexLimbs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident: Ident = input.parse()?;
+        let ty = match ident.to_string().as_str() {
+            "u32" => IntType::U32,
+            "u64" => IntType::U64,
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("expected `u32` or `u64` limbs, found `{}`", ident),
+                ))
+            }
+        };
+        input.parse::<Token![,]>()?;
+        let little_endian = parse_little_endian(input)?;
+
+        let hex: HexString = input.parse()?;
+        hex.ensure_bytes("limbs")?;
+        let limbs = hex.elems().chunks_exact(ty.size());
+        if hex.is_empty() || !limbs.remainder().is_empty() {
+            return Err(syn::Error::new(
+                hex.span(),
+                format!(
+                    "expected a multiple of {} bytes for `{}` limbs, found {}",
+                    ty.size(),
+                    ty.name(),
+                    hex.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            ty,
+            values: limbs
+                .map(|limb| fold_bytes(limb.iter(), little_endian))
+                .collect(),
+            span: hex.span(),
+        })
+    }
+}
+
+impl ToTokens for HexLimbs {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let limbs = self.values.iter().map(|value| {
+            let mut lit = self.ty.literal(*value);
+            lit.set_span(self.span);
+            lit
+        });
+        quote!([#(#limbs),*]).to_tokens(tokens)
+    }
+}
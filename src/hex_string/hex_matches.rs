@@ -0,0 +1,36 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Result, Token};
+
+use super::{HexString, HexStringExt};
+
+/// Input of the `hex_matches!` macro: an expression and a hex string pattern.
+#[derive(Debug)]
+pub struct HexMatches {
+    value: Expr,
+    hex: HexString,
+}
+
+impl Parse for HexMatches {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let value = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let mut hex: HexString = input.parse()?;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            hex.extend(input.parse()?)?;
+        }
+        Ok(Self { value, hex })
+    }
+}
+
+impl ToTokens for HexMatches {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { value, hex } = self;
+        quote!(::core::matches!(#value, #hex)).to_tokens(tokens)
+    }
+}
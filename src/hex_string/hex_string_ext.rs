@@ -0,0 +1,73 @@
+use syn::{spanned::Spanned, Result};
+
+use super::{HexString, HexValue};
+
+/// Modifiers of the `hex!` macros applied to parsed hex strings.
+pub trait HexStringExt {
+    /// Appends the elements of another hex string, keeping their spans.
+    fn extend(&mut self, other: HexString) -> Result<()>;
+    /// Appends a byte spanning the whole hex string.
+    fn push(&mut self, value: u8) -> Result<()>;
+    /// Swaps the high and low nibbles of each byte, including nibble wildcards.
+    fn swap_nibbles(&mut self) -> Result<()>;
+    /// Fails if the hex string contains wildcards, which are only usable in match patterns.
+    fn ensure_bytes(&self, target: &str) -> Result<()>;
+}
+
+/// Replaces the elements of a hex string, keeping its span.
+fn update(hex: &mut HexString, f: impl FnOnce(&mut Vec<HexValue>)) -> Result<()> {
+    let span = hex.span();
+    let mut elems = std::mem::replace(hex, HexString::new(vec![], span)?).into_elems();
+    f(&mut elems);
+    *hex = HexString::new(elems, span)?;
+    Ok(())
+}
+
+impl HexStringExt for HexString {
+    fn extend(&mut self, other: HexString) -> Result<()> {
+        update(self, |elems| elems.extend(other.into_elems()))
+    }
+
+    fn push(&mut self, value: u8) -> Result<()> {
+        let span = self.span();
+        update(self, |elems| elems.push(HexValue::Number { value, span }))
+    }
+
+    fn swap_nibbles(&mut self) -> Result<()> {
+        update(self, |elems| {
+            for elem in elems {
+                match elem {
+                    HexValue::Number { value, .. } => *value = value.rotate_left(4),
+                    HexValue::NibbleWildcard { high, low, .. } => std::mem::swap(high, low),
+                    HexValue::Underscore { .. } | HexValue::DotDot { .. } => {}
+                }
+            }
+        })
+    }
+
+    fn ensure_bytes(&self, target: &str) -> Result<()> {
+        match self
+            .elems()
+            .iter()
+            .find(|elem| !matches!(elem, HexValue::Number { .. }))
+        {
+            Some(elem @ HexValue::NibbleWildcard { .. }) => Err(syn::Error::new(
+                elem.span(),
+                format!(
+                    "nibble wildcards are only valid in match patterns, found `{}` in {}.\n\
+                     help: `?` expands to a range or or-pattern, which is not a byte.",
+                    elem, target
+                ),
+            )),
+            Some(elem) => Err(syn::Error::new(
+                elem.span(),
+                format!(
+                    "wildcards are not allowed in {}, found `{}`.\n\
+                     help: wildcards can only be used in match patterns.",
+                    target, elem
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+}
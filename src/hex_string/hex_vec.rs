@@ -0,0 +1,27 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::Result;
+
+use super::{HexString, HexStringExt};
+
+/// Input of the `hex_vec!` macro: a hex string without wildcards.
+#[derive(Debug)]
+pub struct HexVec {
+    hex: HexString,
+}
+
+impl Parse for HexVec {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let hex: HexString = input.parse()?;
+        hex.ensure_bytes("a `Vec`")?;
+        Ok(Self { hex })
+    }
+}
+
+impl ToTokens for HexVec {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let elems = self.hex.elems();
+        quote!(::std::vec![#(#elems),*]).to_tokens(tokens)
+    }
+}
@@ -5,6 +5,7 @@
 //! [`hex!`](hex!) is a macro which converts string literals (`"7D2B"`) to byte arrays (`[0x7D, 0x2B]`) or match patterns at compile time.
 //!
 //! ```
+//! # use hex_magic::hex;
 //! assert_eq!(hex!("01020304"), [1, 2, 3, 4]);
 //! ```
 //! # `parse_struct!`
@@ -40,12 +41,19 @@
 //! ```
 use proc_macro::TokenStream;
 use quote::quote;
+use syn::parse::{ParseStream, Parser};
 use syn::parse_macro_input;
 
+mod derive_struct;
 mod hex_string;
 mod parse_struct;
-use hex_string::HexString;
-use parse_struct::HexStruct;
+mod write_struct;
+use derive_struct::DeriveParseStruct;
+use hex_string::{
+    Digest, HexDigest, HexDump, HexInput, HexInt, HexLimbs, HexMatches, HexVec, IntType,
+};
+use parse_struct::{HexEnum, HexSlice, HexStruct};
+use write_struct::WriteStruct;
 
 /// Macro which converts string literals (`"7D2B"`) to byte arrays (`[0x7D, 0x2B]`) at compile time.
 ///
@@ -56,80 +64,2580 @@ use parse_struct::HexStruct;
 /// It accepts the following characters in the input string:
 ///
 /// - `'0'...'9'`, `'a'...'f'`, `'A'...'F'` -- hex characters which will be used
-///     in construction of the output byte array
-/// - `' '`, `'\r'`, `'\n'`, `'\t'` -- formatting characters which will be
-///     ignored
-/// - `'_'`, `'.'` -- formatting characters which will be used to create match patterns
+///   in construction of the output byte array
+/// - `' '`, `'\r'`, `'\n'`, `'\t'` and any other Unicode whitespace (such as non-breaking
+///   spaces copied from documents) -- formatting characters which will be ignored
+/// - `':'`, `'-'` -- separator characters which will be ignored between bytes
+///   (`"DE:AD:BE:EF:00:01"`, `"550e8400-e29b-41d4"`). Other separators can be declared with
+///   `sep = "..."` (see [Separators](#separators)) and the length of the groups between them
+///   enforced with `group = N` (see [Groups](#groups))
+/// - `'_'`, `'.'` -- formatting characters which will be used to create match patterns.
+///   A pair of underscores (`"__"`) is always a wildcard byte, while a single `'_'` directly
+///   between two hex bytes is a separator which will be ignored (`"DE_AD_BE_EF"`)
+/// - `'?'` -- a nibble wildcard which will be used to create match patterns
+/// - `"[BYTE; COUNT]"` -- a repetition of a single byte (or a `__` wildcard) `COUNT` times,
+///   where `COUNT` is a decimal number (`"DEAD [00; 4] BEEF"`). Repetitions can't make the
+///   string longer than 4 MiB ([`MAX_LEN`](hex_magic_core::MAX_LEN) bytes)
+/// - `'#'` -- the start of a comment which will be ignored up to the end of the line
+/// - `"0x"`, `"0X"` -- prefixes which will be ignored at the start of the string
+///   or of a whitespace-separated byte group (`"0xDEAD"`, `"0xDE 0xAD"`)
+/// - `"bin(...)"` -- exactly 8 binary digits forming a single byte, which can be grouped with
+///   `_` (`"DE bin(1010_1111)"`). Unlike a `0b` prefix, this can't be confused with hex bytes
+///   such as `0B`
+/// - `"dec(...)"` -- a decimal byte from 0 to 255 (`"dec(10) DEAD"`). Unlike a `d` prefix, this
+///   can't be confused with hex bytes such as `d1`
+/// - `"'ASCII'"` -- characters in single quotes which will be used as they are
+///   (`"'GIF' 38 39 61"`)
+/// - `"\x"`, `"%"` -- escapes followed by exactly two hex digits forming a single byte, as
+///   written by many debugging tools and URL encoding (`r"\xDE\xAD"`, `"%DE%AD"`). Since Rust
+///   string literals have their own `\x` escapes, these are written in raw strings
+/// - `"@"` -- a prefix at the start of a byte group followed by the decimal offset of the next
+///   byte, which is checked while parsing (`"@0 DEAD @2 BEEF"`, see [Offsets](#offsets))
+///
+/// Any other character results in a compile error. On compilers which support spans
+/// of parts of string literals, errors point at the offending characters rather than
+/// at the whole string.
+///
+/// The output only consists of literals and `core` items, so it can be used in `no_std` crates.
 ///
 /// # Example
 ///
 /// ```
-/// use hex_magic::hex;
+/// use hex_magic::hex;
+///
+/// const BYTES: [u8; 3] = hex!("DEAD AF");
+///
+/// fn main() {
+///     assert_eq!(BYTES, [0xDE, 0xAD, 0xAF]);
+///     assert_eq!(hex!("aA aa aA Aa aa"), [0xAA; 5]);
+///     assert_eq!(hex!("0xDE 0xAD 0xAF"), BYTES);
+///     assert_eq!(hex!("DE:AD-AF"), BYTES);
+///     assert_eq!(hex!("[aA; 5]"), [0xAA; 5]);
+///     assert_eq!(hex!("DE bin(1010_1101) AF"), BYTES);
+///     assert_eq!(hex!("0B0C"), [0x0B, 0x0C]);
+///     assert_eq!(hex!("0b00 0b00000001"), [0x0B, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x01]);
+///     assert_eq!(hex!("'GIF' 3839"), *b"GIF89");
+///     assert_eq!(hex!("DE_AD_AF"), BYTES);
+///     assert_eq!(hex!("DE dec(173) AF"), BYTES);
+///     assert_eq!(hex!("d0cf11e0"), [0xD0, 0xCF, 0x11, 0xE0]);
+///     assert_eq!(hex!("d1 d2"), [0xD1, 0xD2]);
+///     assert_eq!(hex!("DE\u{A0}AD\u{2003}AF"), BYTES);
+///     assert_eq!(hex!(r"\xDE\xAD\xAF"), BYTES);
+///     assert_eq!(hex!("%DE%AD%AF"), BYTES);
+///
+///     match [1, 2, 3, 4] {
+///         hex!("AABBCCDD") => panic!("bytes don't match at all"),
+///         hex!("01__FF__") => panic!("[1, _, 0xFF, _] does not match"),
+///         hex!("[__; 3] 05") => panic!("[_, _, _, 5] does not match"),
+///         hex!("01..04") => println!("[1, .., 4] would match"),
+///         hex!("..") => unreachable!("[..] would match"),
+///     }
+/// }
+/// ```
+///
+/// A `..` matches any number of bytes between a fixed head and tail, so it can only be used once
+/// in a pattern. Slices can be matched this way regardless of their length.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// let frame: &[u8] = &[0xDE, 0xAD, 0x01, 0x02, 0x03, 0xBE, 0xEF];
+/// match frame {
+///     hex!("DEAD .. BEEF") => println!("[0xDE, 0xAD, .., 0xBE, 0xEF] matches"),
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// match [1, 2, 3, 4] {
+///     hex!("01..02..04") => {} // `..` can only be used once in a hex string
+///     _ => {}
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(r"\xZZ"); // expected two hex digits after `\x`, got `Z`
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!("bin(1010)"); // expected 8 binary digits in `bin(...)`, found 4
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!("dec(256)"); // decimal byte `dec(256)` is out of range (0-255)
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!("[00; 99999999999]"); // repeat count `99999999999` exceeds the maximum length of a hex string (4194304 bytes)
+/// ```
+///
+/// # Patterns
+///
+/// Hex strings expand to array patterns made of literals, ranges, or-patterns, `_` and `..`, so
+/// they can be used wherever Rust accepts a pattern: `match` arms, `if let`, `while let`,
+/// `let else`, function parameters, [`matches!`] and inside other patterns. A `..` matches any
+/// number of bytes of arrays and slices in any of these positions.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// fn first_two(hex!("__ __"): [u8; 2]) {}
+///
+/// fn main() {
+///     let bytes = [0xDE, 0x01, 0x02, 0xEF];
+///     if let hex!("DE .. EF") = bytes {
+///         println!("[0xDE, .., 0xEF] matches");
+///     }
+///     let hex!("DE ?1 ..") = bytes else { unreachable!() };
+///     assert!(matches!(bytes, hex!("DE ..") | hex!("AA ..")));
+///     if let (Some(hex!("DE ..")), &hex!(".. EF")) = (Some(bytes), &bytes) {
+///         println!("nested patterns match");
+///     }
+///
+///     let mut records: &[u8] = &[0xAA, 0x01, 0xAA, 0x02, 0xFF];
+///     while let hex!("AA __ ..") = records {
+///         records = &records[2..];
+///     }
+///     assert_eq!(records, [0xFF]);
+///     first_two([1, 2]);
+/// }
+/// ```
+///
+/// Forms built by a `const` block can only be used as values: `slice`, `byte_str`, a type
+/// before the string, casts to types other than `i8` and interpolated constants or
+/// `fill(BYTE, COUNT)`. The compiler reports them with "this macro call doesn't expand to
+/// a pattern".
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes: &[u8] = &[0xDE, 0xAD];
+/// if let hex!(slice "DEAD") = bytes {} // this macro call doesn't expand to a pattern
+/// ```
+///
+/// # Concatenation
+///
+/// Multiple hex strings separated by commas are concatenated into a single array.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!("DEAD", "BEEF", "0001"), hex!("DEADBEEF0001"));
+/// ```
+///
+/// Byte strings are copied into the array as they are, which keeps printable parts of the data
+/// readable. Like hex strings, they can be used in match patterns.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(b"HX", "0001", b"END"), *b"HX\x00\x01END");
+/// ```
+///
+/// Constant byte arrays can be interpolated between hex strings. The resulting array is built
+/// in a `const` block, so it can't be used as a match pattern and the hex strings can't contain
+/// wildcards.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const MAGIC: [u8; 2] = hex!("7D2B");
+/// const HEADER: [u8; 6] = hex!("DEAD", MAGIC, "BEEF");
+///
+/// assert_eq!(HEADER, [0xDE, 0xAD, 0x7D, 0x2B, 0xBE, 0xEF]);
+/// ```
+///
+/// Padding can be added with `fill(BYTE, COUNT)`, which repeats a byte a number of times given by
+/// a constant expression, unlike `"[BYTE; COUNT]"` in a hex string which takes a literal count.
+/// It's built in a `const` block like interpolated constants.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const BLOCK: usize = 8;
+/// const PADDED: [u8; BLOCK] = hex!("DEADBEEF", fill(0x00, BLOCK - 4));
+///
+/// assert_eq!(PADDED, [0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x00, 0x00, 0x00]);
+/// assert_eq!(hex!(fill(0xFF, 4)), [0xFF; 4]);
+/// ```
+///
+/// # Comments
+///
+/// Long hex strings can be annotated with comments starting with `#`.
+/// Comments can't be placed between the two digits of a byte.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const HEADER: [u8; 4] = hex!(
+///     "DEAD # magic
+///      0001 # version"
+/// );
+///
+/// assert_eq!(HEADER, [0xDE, 0xAD, 0x00, 0x01]);
+/// ```
+///
+/// # Offsets
+///
+/// `@N` at the start of a byte group asserts that the next byte is at offset `N`, counted from
+/// the start of the string literal, so miscounted bytes in long layouts fail to compile instead
+/// of shifting the following fields. Offsets can't be checked after a `..` wildcard.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const HEADER: [u8; 8] = hex!(
+///     "@0 DEAD        # magic
+///      @2 0001        # version
+///      @4 [00; 2] FF  # flags
+///      @7 dec(10)     # length"
+/// );
+///
+/// assert_eq!(HEADER, [0xDE, 0xAD, 0x00, 0x01, 0x00, 0x00, 0xFF, 0x0A]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!("@0 DEAD @3 BEEF"); // expected the next byte at offset 3, but it's at offset 2
+/// ```
+///
+/// # Separators
+///
+/// `hex!(sep = "...", ...)` declares additional characters which are ignored between bytes like
+/// `':'` and `'-'`, so hex dumps with other delimiters can be pasted as they are. Characters
+/// which already have a meaning in hex strings, such as hex digits, `'_'` and `'.'`, can't be
+/// used as separators.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(sep = "/|", "DE/AD|BE/EF"), [0xDE, 0xAD, 0xBE, 0xEF]);
+/// assert_eq!(hex!(sep = ",", "DE,AD", "BE,EF"), hex!("DEADBEEF"));
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(sep = "/", "D/EAD"); // expected a matching hex digit, got `/`
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(sep = ".", "DE.AD"); // `.` can't be used as a separator
+/// ```
+///
+/// # Groups
+///
+/// `hex!(group = N, ...)` requires every group of hex digits between separators to have exactly
+/// `N` digits, counting wildcards, so a digit lost while copying a grouped dump doesn't silently
+/// shift the following bytes.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(group = 4, "DEAD BEEF 0001"), hex!("DEADBEEF0001"));
+/// assert_eq!(hex!(group = 2, "DE:AD 0xBE-EF"), [0xDE, 0xAD, 0xBE, 0xEF]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(group = 4, "DEAD BEEF 01"); // expected groups of 4 hex digits, found 2
+/// ```
+///
+/// # Length checks
+///
+/// An expected number of bytes can be given after the hex string to turn miscounted
+/// digits into a clear error instead of a type mismatch where the array is used.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const KEY: [u8; 4] = hex!("00112233", 4);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// const KEY: [u8; 4] = hex!("001122", 4); // expected 4 bytes, found 3
+/// ```
+///
+/// Every byte is made of two characters, so an incomplete byte at the end of a hex string is
+/// an error rather than being dropped.
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let key = hex!("DEA"); // unexpected end of input: incomplete byte
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let key = hex!("01_"); // unexpected end of input: expected a matching `_`
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let key = hex!("01."); // unexpected end of input: expected a second `.`
+/// ```
+///
+/// A type, usually an alias of `[u8; N]`, can be given before the first string to assign the
+/// array to it: `hex!(TYPE "...")`. The length is then checked by the type system wherever the
+/// macro is used, pointing at the string, which keeps the size of keys and hashes in a single
+/// place. Typed arrays are values, so they can't contain wildcards or be slices.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// type Key = [u8; 4];
+///
+/// const KEY: Key = hex!(Key "00112233");
+/// assert_eq!(hex!(rev Key "00112233"), [0x33, 0x22, 0x11, 0x00]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// type Key = [u8; 4];
+///
+/// let key = hex!(Key "001122"); // expected an array with a size of 4, found one with a size of 3
+/// ```
+///
+/// # Slices
+///
+/// `hex!(slice "...")` expands to a `&'static [u8]` instead of an array, which can be passed
+/// directly to functions expecting `&[u8]` and used in `const` contexts.
+/// Since slices can't be used as match patterns, wildcards are not allowed in this form.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const MAGIC: &[u8] = hex!(slice "7F454C46");
+///
+/// fn is_elf(bytes: &[u8]) -> bool {
+///     bytes.starts_with(MAGIC)
+/// }
+///
+/// fn main() {
+///     assert!(is_elf(hex!(slice "7F454C46 0201")));
+///     assert!(!is_elf(hex!(slice "4D5A")));
+/// }
+/// ```
+///
+/// Slices of different lengths have the same type, so they can be collected in a table of
+/// signatures without `&hex!(...)[..]`.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const SIGNATURES: &[(&[u8], &str)] = &[
+///     (hex!(slice "4D5A"), "exe"),
+///     (hex!(slice "7F454C46"), "elf"),
+///     (hex!(slice "89 'PNG' 0D0A1A0A"), "png"),
+/// ];
+///
+/// fn file_type(bytes: &[u8]) -> Option<&'static str> {
+///     SIGNATURES
+///         .iter()
+///         .find(|(signature, _)| bytes.starts_with(signature))
+///         .map(|(_, name)| *name)
+/// }
+///
+/// fn main() {
+///     const MAGIC: &[&[u8]] = &[hex!(slice "4D5A"), hex!(slice "7F454C46")];
+///     assert_eq!(MAGIC[1].len(), 4);
+///     assert_eq!(file_type(&hex!("7F454C46 0201")), Some("elf"));
+///     assert_eq!(file_type(&hex!("89504E47 0D0A1A0A")), Some("png"));
+///     assert_eq!(file_type(b"text"), None);
+/// }
+/// ```
+///
+/// # Reversed byte order
+///
+/// `hex!(rev "...")` reverses the order of the parsed bytes, which is useful when a little-endian
+/// value is written in the order it's read. Hex strings containing `..` can't be reversed.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(rev "00112233"), [0x33, 0x22, 0x11, 0x00]);
+/// assert_eq!(hex!(rev "00112233"), 0x00112233u32.to_le_bytes());
+/// ```
+///
+/// # Swapped nibbles
+///
+/// `hex!(nibble_swap "...")` swaps the high and low nibble of each byte, which is how some
+/// telecom and smart card formats store digits (`"DEAD"` becomes `[0xED, 0xDA]`). Nibble
+/// wildcards are swapped as well, while `__` and `..` are left as they are.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(nibble_swap "DEAD"), [0xED, 0xDA]);
+///
+/// match [0x21, 0x43] {
+///     hex!(nibble_swap "12 3?") => println!("digits 1, 2, 3 and any"),
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// # Letter case
+///
+/// `hex!(upper "...")` and `hex!(lower "...")` require all hex digits to be uppercase or
+/// lowercase respectively, which can be used to enforce a consistent style.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(upper "DEADBEEF"), hex!(lower "deadbeef"));
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(upper "DEADbeef"); // expected uppercase hex digits, got `b` at character 5
+/// ```
+///
+/// # Signed bytes
+///
+/// `hex!(as i8 "...")` reinterprets each byte as an `i8`, which is useful for APIs expecting
+/// `[i8; N]` or `[c_char; N]`. With `i8`, the bytes are written as `i8` literals, so a single hex
+/// string can still be used as a match pattern. `c_char` (`c_schar`, `c_uchar`) is platform-
+/// dependent, so the bytes are cast in a `const` block instead and can't contain wildcards.
+/// Other types result in a compile error.
+///
+/// ```
+/// use hex_magic::hex;
+/// use std::ffi::c_char;
+///
+/// assert_eq!(hex!(as i8 "80FF 7F"), [-128, -1, 127]);
+///
+/// const NAME: [c_char; 4] = hex!(as c_char b"HX", "0000");
+/// assert_eq!(NAME, [b'H' as c_char, b'X' as c_char, 0, 0]);
+///
+/// match [-2i8] {
+///     hex!(as i8 "F?") => println!("negative"),
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// # Checksums
+///
+/// `hex!(with_xor_checksum "...")` appends a byte of all bytes XORed together and
+/// `hex!(with_sum_checksum "...")` appends their wrapping sum, which keeps test vectors of
+/// checksummed packets valid when their payload changes. The checksum is computed over the
+/// bytes as they're written (after `rev`) and counts towards the expected length. Hex strings
+/// containing wildcards or interpolated constants result in a compile error.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// assert_eq!(hex!(with_xor_checksum "DEADBEEF"), [0xDE, 0xAD, 0xBE, 0xEF, 0x22]);
+/// assert_eq!(hex!(with_sum_checksum "DEADBEEF"), [0xDE, 0xAD, 0xBE, 0xEF, 0x38]);
+/// assert_eq!(hex!(with_xor_checksum "0102", 3), [0x01, 0x02, 0x03]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let packet = hex!(with_xor_checksum "DE__"); // wildcards are not allowed in a checksum, found `_`
+/// ```
+///
+/// Modifiers can be combined: `hex!(slice rev upper "...")`.
+///
+/// # Nibble wildcards
+///
+/// `?` matches any value of a single nibble, so `"A?"` matches any byte from `0xA0` to `0xAF`
+/// and `"?A"` matches any byte ending with `0xA`. `"??"` is equivalent to `"__"`.
+/// `?` always takes the place of one hex digit and can't be paired with `_` or `.`
+/// (`"_?"` is an error). `".."` is not affected and still matches any number of bytes.
+///
+/// Since match patterns can't contain guards, a known high nibble is expanded into a range pattern
+/// (`0xA0..=0xAF`) and a known low nibble into an or-pattern (`(| 0x0A | 0x1A | ... | 0xFA)`).
+/// Neither of them is a byte, so nibble wildcards can only be used in match patterns
+/// and not in byte arrays. Forms which always produce bytes, such as `slice`, `byte_str`,
+/// checksums, [`hex_bytes!`] and [`hex_str!`], reject them with an error pointing at the `?`.
+/// A plain `hex!` can't tell whether it is expanded in a pattern, so a nibble wildcard used as a
+/// value is reported by the compiler as a mismatched type (`RangeInclusive<u8>` or a closure)
+/// at the same place.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// match [0xA3, 0x5F] {
+///     hex!("B? ??") => unreachable!(),
+///     hex!("A? ?F") => println!("[0xA3, 0x5F] would match"),
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes: [u8; 1] = hex!("A?");
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let bytes = hex!(slice "A?"); // nibble wildcards are only valid in match patterns
+/// ```
+///
+/// # Large strings
+///
+/// The bytes are written as a flat array literal, so long strings don't run into recursion
+/// limits, but every byte is a separate token which the compiler has to check. Parsing the
+/// string takes a small part of the compile time, which grows linearly with the number of bytes,
+/// so a string four times as long takes about four times as long to compile. Hex strings,
+/// including repetitions, included files and concatenated strings, can have at most 4 MiB
+/// ([`MAX_LEN`](hex_magic_core::MAX_LEN), 4194304 bytes). Longer strings are a compile error
+/// rather than running the compiler out of memory, and binary blobs of that size are better kept
+/// in a separate file and embedded with [`include_bytes!`].
+///
+/// `hex!(byte_str "...")` instead writes the bytes as a single byte string literal
+/// (`*b"\xDE\xAD"`), which the compiler handles as one token. Measured with
+/// `benches/large_strings.sh`, which times `cargo build` of a crate containing a single string
+/// in the debug profile, this compiles several times faster: 64 KiB took 0.1 seconds instead of
+/// 0.4, 256 KiB 0.2 seconds instead of 1.6 and 1 MiB 0.9 seconds instead of 5.9.
+/// The result is a value rather than a pattern, so the hex strings can't contain wildcards, and
+/// a byte string is always made of `u8`, so it can't be cast with `as`.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const TABLE: [u8; 8] = hex!(byte_str "0123 4567 89AB CDEF");
+/// assert_eq!(TABLE, hex!("0123 4567 89AB CDEF"));
+/// assert_eq!(hex!(byte_str slice "01", "FF"), &[0x01, 0xFF]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let table = hex!(byte_str "01__"); // wildcards are not allowed in byte strings, found `_`
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let table = hex!(byte_str as i8 "80FF 7F"); // byte strings can't be cast
+/// ```
+///
+/// # Included files
+///
+/// `include "PATH"` reads a hex string from a file at compile time, like [`include_str!`], so
+/// large keys and blobs can be kept out of the source code but are still checked when the crate
+/// is compiled. Unlike with [`include_str!`], the path is relative to the root directory of the
+/// crate calling the macro, which contains its `Cargo.toml` and is given to the macro by Cargo as
+/// `CARGO_MANIFEST_DIR`, so it doesn't depend on the module the macro is called from.
+/// The file is parsed like a string literal in its place, with the same separators, comments
+/// and modifiers, and can be concatenated with other strings. Errors point at the path and give
+/// the line and column in the file.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// // tests/data/key.hex:
+/// // # test key, not used anywhere
+/// // 00112233 44556677
+/// // 8899AABB CCDDEEFF
+/// const KEY: [u8; 16] = hex!(include "tests/data/key.hex");
+/// assert_eq!(KEY[..4], hex!("00112233"));
+/// assert_eq!(hex!(include "tests/data/key.hex", "FF", 17)[16], 0xFF);
+/// ```
+///
+/// The file is also passed to [`include_bytes!`], so the crate is rebuilt when the file changes.
+/// This makes the output a block rather than an array, which can't be used as a pattern. To match
+/// against the bytes of a file, bind them to a constant and use the constant as the pattern.
+///
+/// ```
+/// use hex_magic::hex;
+///
+/// const KEY: [u8; 16] = hex!(include "tests/data/key.hex");
+///
+/// match hex!("00112233 44556677 8899AABB CCDDEEFF") {
+///     KEY => {}
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// match [0u8; 16] {
+///     hex!(include "tests/data/key.hex") => {} // expected pattern, found `{`
+///     _ => {}
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex;
+///
+/// let key = hex!(include "missing.hex"); // couldn't read `missing.hex`: No such file or directory
+/// ```
+#[proc_macro]
+pub fn hex(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexInput);
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro which converts string literals (`"7D2B"`) to byte arrays (`[0x7D, 0x2B]`) at compile time,
+/// for use as values only.
+///
+/// It takes the same input as [`hex!`](hex!), including its modifiers, but fails if the string
+/// contains wildcards. Since `hex!` can't tell whether it's used as a value or as a pattern,
+/// a wildcard in a value results in a confusing error about `_` in expressions, while
+/// `hex_bytes!` points at the wildcard itself.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_bytes;
+///
+/// const MAGIC: [u8; 4] = hex_bytes!("7F 'ELF'");
+///
+/// assert_eq!(MAGIC, [0x7F, 0x45, 0x4C, 0x46]);
+/// assert_eq!(hex_bytes!(rev "0102"), [0x02, 0x01]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex_bytes;
+///
+/// const BYTES: [u8; 2] = hex_bytes!("01__"); // wildcards are not allowed in byte arrays, found `_`
+/// ```
+#[proc_macro]
+pub fn hex_bytes(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexInput);
+    if let Err(err) = input.ensure_bytes("byte arrays") {
+        return TokenStream::from(err.to_compile_error());
+    }
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro which converts string literals (`"7D2B"`) to byte vectors (`vec![0x7D, 0x2B]`).
+///
+/// The string is parsed at compile time using the same rules as [`hex!`](hex!),
+/// but the result is an owned `Vec<u8>`. Wildcards are not allowed since a `Vec`
+/// can't be used as a match pattern.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_vec;
+///
+/// let mut payload = hex_vec!("DEAD BEEF");
+/// payload.extend_from_slice(&[0x00, 0x01]);
+/// assert_eq!(payload, vec![0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x01]);
+/// ```
+#[proc_macro]
+pub fn hex_vec(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexVec);
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro which tests whether a value matches a hex string pattern, expanding to
+/// [`matches!`](core::matches!) with the pattern created by [`hex!`](hex!).
+///
+/// The pattern can contain the `__`, `..` and `?` wildcards. Multiple hex strings separated by
+/// commas are concatenated. The value can be a byte array or a byte slice.
+///
+/// ```
+/// use hex_magic::hex_matches;
+///
+/// let bytes = [0x01, 0x02, 0x03, 0x04];
+/// assert!(hex_matches!(bytes, "01..04"));
+/// assert!(hex_matches!(bytes, "01__ 0?04"));
+/// assert!(!hex_matches!(bytes[..], "FF.."));
+///
+/// let packets = [[0x7D, 0x2B], [0x00, 0x01]];
+/// let count = packets.iter().filter(|packet| hex_matches!(packet, "7D..")).count();
+/// assert_eq!(count, 1);
+/// ```
+#[proc_macro]
+pub fn hex_matches(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexMatches);
+    TokenStream::from(quote!(#input))
+}
+
+fn hex_digest(stream: TokenStream, digest: Digest) -> TokenStream {
+    match (|input: ParseStream| HexDigest::parse_as(input, digest)).parse(stream) {
+        Ok(input) => TokenStream::from(quote!(#input)),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+/// Macro which counts the bytes of string literals (`"DEAD"`) at compile time, resulting in
+/// a `usize` literal (`2usize`).
+///
+/// The string is parsed using the same rules as [`hex!`](hex!). `__` and `?` wildcards count as
+/// single bytes, so the length of a pattern can be taken as well, but `..` isn't allowed.
+/// Since the result is a literal, it can be used in const positions such as array lengths and
+/// const generic arguments, keeping them in sync with the bytes.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::{hex, hex_len};
+///
+/// fn checksum<const N: usize>(bytes: [u8; N]) -> u8 {
+///     bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+/// }
+///
+/// const MAGIC: [u8; hex_len!("7F 45 4C 46")] = hex!("7F 45 4C 46");
+///
+/// assert_eq!(hex_len!("DEAD"), 2);
+/// assert_eq!(hex_len!("7D__ ?0"), 3);
+/// assert_eq!(checksum::<{ hex_len!("0102 03") }>(hex!("0102 03")), 6);
+/// assert_eq!(MAGIC.len(), 4);
+/// ```
+#[proc_macro]
+pub fn hex_len(stream: TokenStream) -> TokenStream {
+    hex_digest(stream, Digest::Len)
+}
+
+/// Macro which converts string literals (`"7D2B"`) to byte arrays along with their length
+/// (`([0x7D, 0x2B], 2usize)`).
+///
+/// The string is parsed using the same rules as [`hex!`](hex!) but can't contain wildcards.
+/// Both values are constant, which is useful for tables of magic numbers.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_with_len;
+///
+/// const MAGIC: ([u8; 4], usize) = hex_with_len!("7F454C46");
+///
+/// assert_eq!(MAGIC, ([0x7F, 0x45, 0x4C, 0x46], 4));
+/// ```
+#[proc_macro]
+pub fn hex_with_len(stream: TokenStream) -> TokenStream {
+    hex_digest(stream, Digest::WithLen)
+}
+
+/// Macro which computes the CRC-32 checksum of the bytes of a string literal at compile time,
+/// resulting in a `u32` value.
+///
+/// The string is parsed using the same rules as [`hex!`](hex!) but can't contain wildcards.
+/// The checksum is the common CRC-32 used by zlib, PNG and Ethernet (reflected polynomial
+/// `0xEDB88320`).
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_crc32;
+///
+/// const CHECKSUM: u32 = hex_crc32!("31 32 33 34 35 36 37 38 39"); // "123456789"
+///
+/// assert_eq!(CHECKSUM, 0xCBF43926);
+/// assert_eq!(hex_crc32!(""), 0);
+/// ```
+#[proc_macro]
+pub fn hex_crc32(stream: TokenStream) -> TokenStream {
+    hex_digest(stream, Digest::Crc32)
+}
+
+/// Macro which normalizes string literals (`"de:ad be-ef"`) to uppercase hex digits
+/// (`"DEADBEEF"`), resulting in a `&'static str`.
+///
+/// The string is parsed using the same rules as [`hex!`](hex!) but can't contain wildcards,
+/// since they aren't hex digits. This is useful for logging and round-trip tests.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_str;
+///
+/// const ID: &str = hex_str!("de ad be ef");
+///
+/// assert_eq!(ID, "DEADBEEF");
+/// assert_eq!(hex_str!("0x7d2b 'A' dec(10)"), "7D2B410A");
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex_str;
+///
+/// let id = hex_str!("DE__"); // wildcards are not allowed in a string, found `_`
+/// ```
+#[proc_macro]
+pub fn hex_str(stream: TokenStream) -> TokenStream {
+    hex_digest(stream, Digest::Str)
+}
+
+/// Macro which formats bytes as a classic hexdump, resulting in a `String`.
+///
+/// The argument can be any expression giving bytes (`&[u8]`, `Vec<u8>` or the arrays of
+/// [`hex!`](hex!)). Each line shows the offset of its first byte, up to 16 bytes as uppercase
+/// hex digits and the same bytes as ASCII, where non-printable bytes are shown as `.`. This
+/// makes it easy to compare the bytes produced by the other macros while debugging.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::{hex, hexdump};
+///
+/// let dump = hexdump!(hex!("'HX' 00 01 [FF; 14] 'end'"));
+/// assert_eq!(
+///     dump,
+///     "00000000  48 58 00 01 FF FF FF FF FF FF FF FF FF FF FF FF  |HX..............|\n\
+///      00000010  FF FF 65 6E 64                                   |..end|\n"
+/// );
+/// assert_eq!(hexdump!(&[] as &[u8]), "");
+/// ```
+#[proc_macro]
+pub fn hexdump(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexDump);
+    TokenStream::from(quote!(#input))
+}
+
+fn hex_int(stream: TokenStream, ty: IntType) -> TokenStream {
+    match (|input: ParseStream| HexInt::parse_as(input, ty)).parse(stream) {
+        Ok(input) => TokenStream::from(quote!(#input)),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+/// Macro which converts string literals (`"7D2B"`) to `u16` values (`0x7D2Bu16`) at compile time.
+///
+/// The hex string must contain exactly 2 bytes and no wildcards. Bytes are read in big-endian
+/// order unless the string is preceded by `le`. See [`hex_u32!`](hex_u32!) for details.
+#[proc_macro]
+pub fn hex_u16(stream: TokenStream) -> TokenStream {
+    hex_int(stream, IntType::U16)
+}
+
+/// Macro which converts string literals (`"DEADBEEF"`) to `u32` values (`0xDEADBEEFu32`)
+/// at compile time.
+///
+/// The string is parsed using the same rules as [`hex!`](hex!) but must contain
+/// exactly 4 bytes and no wildcards. By default, the bytes are read in big-endian order
+/// (like `u32::from_be_bytes`), which can be made explicit with `be`.
+/// Little-endian order (like `u32::from_le_bytes`) can be selected with `le`.
+///
+/// The same rules apply to [`hex_u16!`](hex_u16!), [`hex_u64!`](hex_u64!)
+/// and [`hex_u128!`](hex_u128!).
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::{hex_u16, hex_u32};
+///
+/// const MAGIC: u32 = hex_u32!("DEADBEEF");
+///
+/// assert_eq!(MAGIC, 0xDEADBEEF);
+/// assert_eq!(hex_u32!(le "DEADBEEF"), 0xEFBEADDE);
+/// assert_eq!(hex_u16!(be "7D2B"), u16::from_be_bytes([0x7D, 0x2B]));
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex_u32;
+///
+/// let value = hex_u32!("DEADBE"); // expected 4 bytes for a `u32`, found 3
+/// ```
+#[proc_macro]
+pub fn hex_u32(stream: TokenStream) -> TokenStream {
+    hex_int(stream, IntType::U32)
+}
+
+/// Macro which converts string literals to `u64` values at compile time.
+///
+/// The hex string must contain exactly 8 bytes and no wildcards. Bytes are read in big-endian
+/// order unless the string is preceded by `le`. See [`hex_u32!`](hex_u32!) for details.
+#[proc_macro]
+pub fn hex_u64(stream: TokenStream) -> TokenStream {
+    hex_int(stream, IntType::U64)
+}
+
+/// Macro which converts string literals to `u128` values at compile time.
+///
+/// The hex string must contain exactly 16 bytes and no wildcards. Bytes are read in big-endian
+/// order unless the string is preceded by `le`. See [`hex_u32!`](hex_u32!) for details.
+#[proc_macro]
+pub fn hex_u128(stream: TokenStream) -> TokenStream {
+    hex_int(stream, IntType::U128)
+}
+
+/// Macro which splits string literals into arrays of `u32` or `u64` limbs at compile time, for
+/// big integer constants such as the 256-bit values of cryptographic code.
+///
+/// The limb type is followed by a hex string with no wildcards and a number of bytes which is a
+/// multiple of the size of a limb. Each group of bytes is converted to a limb like
+/// [`hex_u32!`](hex_u32!) and [`hex_u64!`](hex_u64!) do, in big-endian order unless the string is
+/// preceded by `le`. Limbs are kept in the order of the string, so a big-endian string results in
+/// the most significant limb first, while a little-endian string results in the least significant
+/// limb first.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::hex_limbs;
+///
+/// const P: [u64; 4] = hex_limbs!(u64, be "
+///     FFFFFFFF FFFFFFFF FFFFFFFF FFFFFFFF
+///     FFFFFFFF FFFFFFFF FFFFFFFE FFFFFC2F
+/// ");
+///
+/// assert_eq!(P, [u64::MAX, u64::MAX, u64::MAX, 0xFFFFFFFE_FFFFFC2F]);
+/// assert_eq!(hex_limbs!(u32, le "01000000 02000000"), [1, 2]);
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::hex_limbs;
+///
+/// let limbs = hex_limbs!(u64, "DEADBEEF"); // expected a multiple of 8 bytes for `u64` limbs, found 4
+/// ```
+#[proc_macro]
+pub fn hex_limbs(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexLimbs);
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro for parsing bytes from [`Read`](std::io::Read) readers into structs
+/// with the ability to skip padding bytes.
+///
+/// # Syntax
+///
+/// ```text
+/// parse_struct!([counted] [threaded] [collect] [exact] [trace] [async] [seekable] [buffered] READER [, SCRATCH] => STRUCT {
+///     ...
+///     FIELD: [BINDING @] BYTE_PATTERN [if GUARD] [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: TYPE @ [le|be|nul] BYTE_PATTERN [if GUARD] [; validate CLOSURE],
+///     FIELD: [TYPE; N] @ [le|be] BYTE_PATTERN [if GUARD] [; validate CLOSURE],
+///     FIELD: Option<TYPE> @? [le|be|nul] BYTE_PATTERN,
+///     FIELD: [BINDING @] boxed BYTE_PATTERN [if GUARD] [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: bits [msb|lsb] { NAME: WIDTH, ... } @ BYTE_PATTERN [if GUARD] [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: [BINDING @] vec(LENGTH [, max = N]) [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: [BINDING @] until(BYTE) [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: [BINDING @] cstr [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: [BINDING @] rest [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: STRUCT { FIELDS },
+///     FIELD: [STRUCT; COUNT [, max = N]] @ { FIELDS },
+///     FIELD: virtual [=> EXPRESSION] [; validate CLOSURE],
+///     FIELD: seek(OFFSET) ...,
+///     @reader(READER) FIELD: ...,
+///     ...
+/// })
+/// ```
+///
+/// First, the macro expects a reader or an expression the result of which would be a reader.
+/// The reader is followed by `=>` and then by a modified form of struct instantiation.
+///
+/// The basic syntax of struct instantiation takes the form of `FIELD: BYTE_PATTERN`. This will assign
+/// the read bytes (`[u8; N]`) to the given field if it matches the pattern.
+/// For more advanced scenarios, such as for converting the bytes to other types,
+/// bindings and expressions can be used:
+/// `FIELD: BINDING @ BYTE_PATTERN => EXPRESSION`.
+/// In this case, the result of `EXPRESSION` will be assigned to `FIELD`.
+/// If the expression converts the binding with `from_le_bytes`, `from_be_bytes` or
+/// `from_ne_bytes` of a primitive number type, the length of the pattern is checked against
+/// the size of the type.
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Data {
+///     b: u32,
+/// }
+///
+/// let bytes = [0xAA, 0xBB];
+/// let data = parse_struct!(bytes.as_ref() => Data {
+///     b: buf @ "AA__" => u32::from_le_bytes(*buf), // expected 4 bytes for `u32::from_le_bytes`, found 2
+/// });
+/// ```
+///
+/// Fields of primitive number types (`u8` to `u128`, `i8` to `i128`, `f32` and `f64`) can be
+/// converted automatically with `FIELD: TYPE @ [le|be] BYTE_PATTERN`. The byte order is given by
+/// `le` (`from_le_bytes`) or `be` (`from_be_bytes`), either after `@` or as a `#[le]` or `#[be]`
+/// attribute on the field. The pattern must have as many bytes as the type.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     version: u8,
+///     length: u16,
+///     offset: u32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x20];
+///     let header = parse_struct!(bytes.as_ref() => Header {
+///         version: u8 @ "01",
+///         length: u16 @ le "____",
+///         #[be]
+///         offset: u32 @ "________",
+///     })?;
+///     assert_eq!(header.version, 1);
+///     assert_eq!(header.length, 0x1000);
+///     assert_eq!(header.offset, 0x20);
+///     Ok(())
+/// }
+/// ```
+///
+/// Other outer attributes on a field, such as `#[allow(...)]` or `#[cfg(...)]`, are kept on the
+/// field of the struct expression. Inner attributes (`#![...]`) can't be given to fields.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     version: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01];
+///     let header = parse_struct!(bytes.as_ref() => Header {
+///         #[allow(clippy::identity_op)]
+///         version: buf @ "01" => buf[0] * 1,
+///     })?;
+///     assert_eq!(header.version, 1);
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     version: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01];
+///     let header = parse_struct!(bytes.as_ref() => Header {
+///         #![allow(unused)] // inner attributes can't be given to fields
+///         version: u8 @ "01",
+///     })?;
+///     Ok(())
+/// }
+/// ```
+///
+/// Structs where one byte order dominates can set a default with an `#[endian(le)]` or
+/// `#[endian(be)]` attribute before the struct, which applies to every typed field without its
+/// own byte order, including those of nested structs unless they set their own default.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     length: u16,
+///     offset: u32,
+///     id: u16,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x10, 0x00, 0x20, 0x00, 0x00, 0x00, 0x7D, 0x2B];
+///     let header = parse_struct!(bytes.as_ref() => #[endian(le)] Header {
+///         length: u16 @ "____",
+///         offset: u32 @ "________",
+///         id: u16 @ be "____",
+///     })?;
+///     assert_eq!(header.length, 0x10);
+///     assert_eq!(header.offset, 0x20);
+///     assert_eq!(header.id, 0x7D2B);
+///     Ok(())
+/// }
+/// ```
+///
+/// Signed integers and floats are converted the same way, which is common in scientific and
+/// media formats.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Sample {
+///     offset: i16,
+///     value: f32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0xFF, 0xFE, 0x00, 0x00, 0xC0, 0x3F];
+///     let sample = parse_struct!(bytes.as_ref() => Sample {
+///         offset: i16 @ be "____",
+///         value: f32 @ le "________",
+///     })?;
+///     assert_eq!(sample.offset, -2);
+///     assert_eq!(sample.value, 1.5);
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Sample {
+///     value: f64,
+/// }
+///
+/// let bytes = [0x00; 4];
+/// let sample = parse_struct!(bytes.as_ref() => Sample {
+///     value: f64 @ le "________", // expected 8 bytes for a `f64`, found 4
+/// });
+/// ```
+///
+/// Arrays of these types (`[TYPE; N]`) are converted element by element from consecutive chunks
+/// of bytes in the given byte order, so the pattern must have `N` times as many bytes as the
+/// element type.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Frame {
+///     samples: [u16; 4],
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00];
+///     let frame = parse_struct!(bytes.as_ref() => Frame {
+///         samples: [u16; 4] @ le "[__; 8]",
+///     })?;
+///     assert_eq!(frame.samples, [1, 2, 3, 4]);
+///     Ok(())
+/// }
+/// ```
+///
+/// Formats such as TIFF and UTF-16 start with a byte order mark giving the byte order of the
+/// numbers that follow. A field of 2 or 4 bytes marked with `#[bom]` is read as usual and then
+/// determines the byte order of later typed fields without `le` or `be`, including those of
+/// nested structs, which check it at runtime instead of using the default of the struct. `II`
+/// and `FF FE` (or `FF FE 00 00`) are little endian, while `MM` and `FE FF` (or `00 00 FE FF`)
+/// are big endian. Any other mark results in an
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read, Result};
+///
+/// struct Tiff {
+///     order: [u8; 2],
+///     magic: u16,
+///     offset: u32,
+/// }
+///
+/// fn parse_tiff(bytes: &[u8]) -> Result<Tiff> {
+///     parse_struct!(bytes => Tiff {
+///         #[bom]
+///         order: "____",
+///         magic: u16 @ "____" if magic == 42,
+///         offset: u32 @ "________",
+///     })
+/// }
+///
+/// fn main() -> Result<()> {
+///     let little = parse_tiff(&[0x49, 0x49, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00])?;
+///     assert_eq!(little.order, *b"II");
+///     assert_eq!(little.offset, 8);
+///     let big = parse_tiff(&[0x4D, 0x4D, 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08])?;
+///     assert_eq!(big.offset, 8);
+///     let unknown = parse_tiff(&[0x49, 0x4D, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00]);
+///     assert_eq!(unknown.err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+///     Ok(())
+/// }
+/// ```
+///
+/// `if GUARD` after the pattern adds a condition the field must meet, such as a checksum or
+/// a length matching earlier fields. The guard is checked after the field is read and can refer
+/// to the field's value by its name as well as to the binding and earlier fields. A false guard
+/// results in an [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Packet {
+///     a: u8,
+///     b: u8,
+///     sum: u16,
+/// }
+///
+/// let bytes = [0x10, 0x20, 0x30, 0x00];
+/// let packet = parse_struct!(bytes.as_ref() => Packet {
+///     a: u8 @ "__",
+///     b: u8 @ "__",
+///     sum: u16 @ le "____" if sum == a as u16 + b as u16,
+/// });
+/// assert!(packet.is_ok());
+///
+/// let bytes = [0x10, 0x20, 0x31, 0x00];
+/// let packet = parse_struct!(bytes.as_ref() => Packet {
+///     a: u8 @ "__",
+///     b: u8 @ "__",
+///     sum: buf @ "____" if u16::from_le_bytes(*buf) == a as u16 + b as u16
+///         => u16::from_le_bytes(*buf),
+/// });
+/// assert_eq!(packet.err().unwrap().kind(), ErrorKind::InvalidData);
+/// ```
+///
+/// Typed fields are already converted, so like without a guard they can't be followed by
+/// `=> EXPRESSION`.
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Header {
+///     length: u16,
+/// }
+///
+/// let bytes = [0x00, 0x10];
+/// let header = parse_struct!(bytes.as_ref() => Header {
+///     length: u16 @ be "____" if length > 0 => length * 2, // typed fields can't be followed by an expression
+/// });
+/// ```
+///
+/// `; validate CLOSURE` at the end of a field checks the final value of the field, after the
+/// expression or type conversion, by calling the closure with a reference to it. Like a false
+/// guard, a closure returning `false` results in an
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Header {
+///     version: u8,
+///     len: u16,
+/// }
+///
+/// let bytes = [0x03, 0x10, 0x00];
+/// let header = parse_struct!(bytes.as_ref() => Header {
+///     version: v @ "__" => u8::from_le_bytes(*v); validate |v| *v <= 3,
+///     len: u16 @ le "____"; validate |len| len % 4 == 0,
+/// });
+/// assert_eq!(header.unwrap().len, 16);
+///
+/// let bytes = [0x04, 0x10, 0x00];
+/// let header = parse_struct!(bytes.as_ref() => Header {
+///     version: v @ "__" => u8::from_le_bytes(*v); validate |v| *v <= 3,
+///     len: u16 @ le "____"; validate |len| len % 4 == 0,
+/// });
+/// assert_eq!(header.err().unwrap().kind(), ErrorKind::InvalidData);
+/// ```
+///
+/// Generic structs can be given with or without their arguments, which are written with
+/// turbofishes where needed (`Packet<u16>` or `Packet::<u16>`). Without them, the type
+/// parameters are inferred from the expressions of the fields or from the expected type.
+/// This works the same for nested structs, records, [`parse_enum!`](parse_enum!) and
+/// `write_struct!`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Packet<T> {
+///     kind: u8,
+///     value: T,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x02, 0x00];
+///     let packet = parse_struct!(bytes.as_ref() => Packet<u16> {
+///         kind: u8 @ "01",
+///         value: u16 @ le "____",
+///     })?;
+///     assert_eq!(packet.value, 2);
+///
+///     let packet = parse_struct!(bytes.as_ref() => Packet {
+///         kind: u8 @ "01",
+///         value: b @ "____" => char::from(b[0]),
+///     })?;
+///     assert_eq!(packet.value, '\u{2}');
+///
+///     let nested = parse_struct!(bytes.as_ref() => Packet::<Packet<u8>> {
+///         kind: u8 @ "01",
+///         value: Packet { kind: u8 @ "02", value: u8 @ "__" },
+///     })?;
+///     assert_eq!(nested.value.kind, 2);
+///     Ok(())
+/// }
+/// ```
+///
+/// `String` fields are converted with `String::from_utf8`, resulting in an
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error if the bytes
+/// aren't valid UTF-8. With `nul`, C-style strings padded with null bytes are cut at the
+/// first `0x00`. `String` fields can also be read with `vec(LENGTH)`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Entry {
+///     name: String,
+///     comment: String,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = b"HEX\0\0\0\0\0\x05magic";
+///     let entry = parse_struct!(bytes.as_ref() => Entry {
+///         name: String @ nul "________________",
+///         _: "05",
+///         comment: String @ vec(5),
+///     })?;
+///     assert_eq!(entry.name, "HEX");
+///     assert_eq!(entry.comment, "magic");
+///     Ok(())
+/// }
+/// ```
+///
+/// Typed fields declared as `Option<TYPE> @? ...` are optional: if the input ends before the
+/// first byte of the field, it's set to `None`. Input ending in the middle of the field is
+/// still an error. This is useful for trailing fields added in later versions of a format.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     version: u8,
+///     flags: Option<u16>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let header = parse_struct!([0x01].as_ref() => Header {
+///         version: u8 @ "__",
+///         flags: Option<u16> @? le "____",
+///     })?;
+///     assert_eq!(header.flags, None);
+///
+///     let header = parse_struct!([0x02, 0x80, 0x00].as_ref() => Header {
+///         version: u8 @ "__",
+///         flags: Option<u16> @? le "____",
+///     })?;
+///     assert_eq!(header.flags, Some(0x80));
+///     Ok(())
+/// }
+/// ```
+///
+/// A special `_` field is available for matching against bytes without including them in the
+/// struct. `_` fields can be specified multiple times and
+/// can be used for skipping padding bytes or for matching against bytes without including them in
+/// the struct. Bindings and expressions can be used with these fields as well but expressions must
+/// evaluate to ().
+///
+/// Patterns can be any of:
+/// - `[1, 2, 3, _, 5]` - standard byte array patterns
+/// - `b"byte string!"` - byte strings
+/// - `ci b"GET"` - byte strings matching ASCII letters of either case
+/// - `"FF00FF 00FF00"` - hex strings usable with the [`hex!`](hex!) macro
+/// - `MAGIC` - paths of constant byte arrays, such as ones created by [`hex!`](hex!)
+///
+/// Patterns can include `_` but not `..` wildcards since the length of the pattern is
+/// used to determine the amount of bytes to read. The length of a constant is only known after
+/// the macro is expanded, so the number of bytes to read is taken from `MAGIC.len()` in a
+/// `const` item and the bytes are compared with `!=`.
+/// Constants can't be used with typed fields.
+///
+/// ```
+/// use hex_magic::{hex, parse_struct};
+/// use std::io::{Read, Result};
+///
+/// const MAGIC: [u8; 4] = hex!("7F454C46");
+///
+/// struct Header {
+///     magic: [u8; 4],
+///     class: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = hex!("7F454C46 02");
+///     let header = parse_struct!(bytes.as_ref() => Header {
+///         magic: MAGIC,
+///         class: u8 @ "__",
+///     })?;
+///     assert_eq!(header.magic, MAGIC);
+///     Ok(())
+/// }
+/// ```
+///
+/// `ci` before a byte string matches its ASCII letters case-insensitively, as in the tokens of
+/// many text-based protocols. Each letter is matched by an or-pattern of its two cases, so
+/// `ci` patterns can also be used as tags of [`parse_enum!`](parse_enum!).
+/// `write_struct!` writes them as given.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Request {
+///     method: [u8; 3],
+/// }
+///
+/// fn main() -> Result<()> {
+///     for line in [b"GET /", b"get /", b"Get /"] {
+///         let request = parse_struct!(line.as_ref() => Request {
+///             method: ci b"GET",
+///             _: b" /",
+///         })?;
+///         assert!(request.method.eq_ignore_ascii_case(b"GET"));
+///     }
+///     assert!(parse_struct!(b"PUT /".as_ref() => Request {
+///         method: ci b"GET",
+///         _: b" /",
+///     })
+///     .is_err());
+///     Ok(())
+/// }
+/// ```
+///
+/// Elements of byte array patterns can be ranges of byte values (`0x01..=0x7F`, `..0x80`),
+/// each of which matches a single byte.
+///
+/// ```
+/// use hex_magic::{hex, parse_struct};
+/// use std::io::{Read, Result};
+///
+/// struct Packet {
+///     version: u8,
+///     flags: [u8; 2],
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = hex!("03 00 41");
+///     let packet = parse_struct!(bytes.as_ref() => Packet {
+///         version: u8 @ [0x01..=0x7F],
+///         flags: [_, b'A'..=b'Z'],
+///     })?;
+///     assert_eq!(packet.version, 3);
+///     assert_eq!(packet.flags, [0x00, 0x41]);
+///
+///     let bytes = hex!("80 00 41");
+///     assert!(parse_struct!(bytes.as_ref() => Packet {
+///         version: u8 @ [0x01..=0x7F],
+///         flags: [_, b'A'..=b'Z'],
+///     })
+///     .is_err());
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Packet {
+///     version: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x03u8];
+///     let packet = parse_struct!(bytes.as_ref() => Packet {
+///         version: u8 @ [0x01..], // ranges of byte values must have an end
+///     })?;
+///     Ok(())
+/// }
+/// ```
+///
+/// `boxed` before a byte array, byte string or hex string reads the bytes into a
+/// `Box<[u8]>` instead of an array, so large fields don't take up space on the stack, neither
+/// in the struct nor in the buffer the macro reads fields into.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Page {
+///     id: u16,
+///     data: Box<[u8]>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let mut bytes = vec![0x01, 0x00];
+///     bytes.extend_from_slice(&[0xAA; 4096]);
+///     let page = parse_struct!(bytes.as_slice() => Page {
+///         id: u16 @ le "____",
+///         data: boxed "[__; 4096]",
+///     })?;
+///     assert_eq!(page.id, 1);
+///     assert_eq!(page.data.len(), 4096);
+///     Ok(())
+/// }
+/// ```
+///
+/// `bits { NAME: WIDTH, ... } @` before a hex string, byte string or byte array of up to 16
+/// bytes splits the bytes into bit fields, for formats packing several values into a byte.
+/// Each bit field is bound to its name as the smallest unsigned integer holding `WIDTH` bits,
+/// both in the guard and expression of the field and in later fields, so bit fields can be
+/// assigned to struct members with `virtual` fields. The widths must add up to the number of
+/// bits of the bytes, where unused bits are named `_`. By default, or with `bits msb`, the first
+/// bit field is in the most significant bits of the first byte, as in most network protocols.
+/// With `bits lsb`, the first bit field is in the least significant bits and the bytes are in
+/// little-endian order, as in C bit fields on little-endian targets.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     version: u8,
+///     header_len: u8,
+///     dont_fragment: bool,
+///     offset: u16,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x45, 0x40, 0x12];
+///     let header = parse_struct!(bytes.as_ref() => Header {
+///         _: bits { version: 4, ihl: 4 } @ "4?" if ihl >= 5,
+///         _: bits { _: 1, df: 1, _: 1, offset: 13 } @ "____",
+///         version: virtual => version,
+///         header_len: virtual => ihl * 4,
+///         dont_fragment: virtual => df == 1,
+///         offset: virtual => offset,
+///     })?;
+///     assert_eq!(header.version, 4);
+///     assert_eq!(header.header_len, 20);
+///     assert!(header.dont_fragment);
+///     assert_eq!(header.offset, 0x12);
+///
+///     let flags = parse_struct!(bytes.as_ref() => Header {
+///         _: "__",
+///         offset: bits lsb { low: 4, high: 12 } @ "____" => high,
+///         version: virtual => low as u8,
+///         header_len: virtual => 0,
+///         dont_fragment: virtual => false,
+///     })?;
+///     assert_eq!(flags.version, 0);
+///     assert_eq!(flags.offset, 0x124);
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Flags {
+///     kind: u8,
+/// }
+///
+/// let bytes = [0x45];
+/// let flags = parse_struct!(bytes.as_ref() => Flags {
+///     kind: bits { kind: 3, level: 4 } @ "__" => kind, // expected bit fields adding up to 8 bits, found 7
+/// });
+/// ```
+///
+/// `_: skip(N)` reads and discards `N` bytes without matching them, which is useful for
+/// skipping large reserved regions.
+///
+/// `_: ..` reads and discards all remaining bytes up to the end of the input, which is useful
+/// when only a header at the start of the input is needed. It must be the last field.
+///
+/// `rest` keeps the remaining bytes instead, reading them into a `Vec<u8>`, or borrowing them as
+/// a `&[u8]` with [`parse_slice!`](parse_slice!), so trailing payloads of unknown length don't
+/// need a separate read after the macro. Like `_: ..`, it must be the last field.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Message {
+///     kind: u8,
+///     payload: Vec<u8>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x02, 0x68, 0x69];
+///     let message = parse_struct!(bytes.as_ref() => Message {
+///         kind: u8 @ "__",
+///         payload: rest,
+///     })?;
+///     assert_eq!(message.kind, 2);
+///     assert_eq!(message.payload, b"hi");
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Message {
+///     kind: u8,
+///     payload: Vec<u8>,
+/// }
+///
+/// let bytes = [0x02, 0x68, 0x69];
+/// let message = parse_struct!(bytes.as_ref() => Message {
+///     payload: rest,
+///     kind: u8 @ "__", // `rest` must be the last field
+/// });
+/// ```
+///
+/// `_: align(N)` reads and discards the padding bytes up to the next multiple of `N`,
+/// counted from the start of the struct, so the padding doesn't need to be updated when
+/// earlier fields change size.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Entry {
+///     kind: u8,
+///     value: u32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x00, 0x00, 0x00, 0x2A, 0x00, 0x00, 0x00];
+///     let entry = parse_struct!(bytes.as_ref() => Entry {
+///         kind: u8 @ "__",
+///         _: align(4),
+///         value: u32 @ le "________",
+///     })?;
+///     assert_eq!(entry.value, 42);
+///     Ok(())
+/// }
+/// ```
+///
+/// `_: skip_to(MAGIC)` reads and discards bytes up to and including the first occurrence of
+/// `MAGIC`, leaving the reader just after it, which resynchronizes stream parsers on a sentinel
+/// after corrupted data. `MAGIC` is an expression giving the bytes, such as a byte string,
+/// [`hex!`](hex!) or a constant array. Bytes are read one at a time, so unbuffered readers are
+/// better wrapped in a `BufReader`. If the input ends before `MAGIC`, the result is an
+/// [`std::io::ErrorKind::UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Header {
+///     class: u8,
+/// }
+///
+/// let bytes = [0x00, 0x7F, 0x7F, b'E', b'L', b'F', 0x02];
+/// let header = parse_struct!(bytes.as_ref() => Header {
+///     _: skip_to(b"\x7FELF"),
+///     class: u8 @ "__",
+/// });
+/// assert_eq!(header.unwrap().class, 2);
+///
+/// let header = parse_struct!(bytes.as_ref() => Header {
+///     _: skip_to(b"\xCA\xFE"),
+///     class: u8 @ "__",
+/// });
+/// assert_eq!(header.err().unwrap().kind(), ErrorKind::UnexpectedEof);
+/// ```
+///
+/// `FIELD: virtual => EXPRESSION` reads no bytes and assigns the expression to the field, for
+/// members which aren't part of the input, such as `PhantomData` markers or values computed
+/// from earlier fields. Without an expression, the field is `Default::default()`. `write_struct!`
+/// skips virtual fields.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+/// use std::marker::PhantomData;
+///
+/// struct Packet<T> {
+///     len: u8,
+///     size: usize,
+///     _marker: PhantomData<T>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x04];
+///     let packet = parse_struct!(bytes.as_ref() => Packet::<u32> {
+///         len: u8 @ "__",
+///         size: virtual => len as usize * 4,
+///         _marker: virtual,
+///     })?;
+///     assert_eq!(packet.size, 16);
+///     Ok(())
+/// }
+/// ```
+///
+/// `FIELD: vec(LENGTH)` reads a number of bytes only known at runtime into a `Vec<u8>`.
+/// `LENGTH` can be any expression convertible to `usize`. Values of earlier named fields
+/// are in scope under their field names, so length prefixes can be read first and
+/// referred to by later fields. Fields can't refer to themselves or to fields declared after them.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Chunk {
+///     len: u8,
+///     data: Vec<u8>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x03, 0xAA, 0xBB, 0xCC];
+///     let chunk = parse_struct!(bytes.as_ref() => Chunk {
+///         len: u8 @ "__",
+///         data: vec(len),
+///     })?;
+///     assert_eq!(chunk.data, vec![0xAA, 0xBB, 0xCC]);
+///     Ok(())
+/// }
+/// ```
+///
+/// Since `LENGTH` usually comes from the input, `vec(LENGTH, max = N)` limits it to at most
+/// `N` bytes. Larger lengths result in an error of kind `InvalidData` before anything is
+/// allocated. `[STRUCT; COUNT, max = N]` limits the number of records in the same way.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Chunk {
+///     len: u32,
+///     data: Vec<u8>,
+/// }
+///
+/// let bytes = [0xFF, 0xFF, 0xFF, 0xFF];
+/// let result = parse_struct!(bytes.as_ref() => Chunk {
+///     len: u32 @ le "________",
+///     data: vec(len, max = 1024),
+/// });
+/// assert_eq!(result.err().unwrap().kind(), ErrorKind::InvalidData);
+/// ```
+///
+/// `FIELD: until(BYTE)` reads bytes up to a delimiter into a `Vec<u8>`, for newline-delimited
+/// records and other text-like data. The delimiter is read but not included, and `cstr` is
+/// short for `until(0x00)`, which reads null-terminated C strings. Both can also be converted to
+/// `String` with `FIELD: String @ until(BYTE)`. The reader must implement
+/// [`BufRead`](std::io::BufRead), and reaching the end of the input before the delimiter
+/// results in an error of kind `InvalidData`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{BufRead, ErrorKind, Read, Result};
+///
+/// struct Entry {
+///     name: String,
+///     line: Vec<u8>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = b"key\0value\n";
+///     let entry = parse_struct!(bytes.as_ref() => Entry {
+///         name: String @ cstr,
+///         line: until(b'\n'),
+///     })?;
+///     assert_eq!(entry.name, "key");
+///     assert_eq!(entry.line, b"value");
+///
+///     let bytes = b"key";
+///     let result = parse_struct!(bytes.as_ref() => Entry {
+///         name: String @ cstr,
+///         line: until(b'\n'),
+///     });
+///     assert_eq!(result.err().unwrap().kind(), ErrorKind::InvalidData);
+///     Ok(())
+/// }
+/// ```
+///
+/// `FIELD: STRUCT { FIELDS }` parses a nested struct from the same reader, using the same
+/// syntax as the outer struct. This allows a whole binary layout to be described by a single
+/// macro call.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Header {
+///     magic: [u8; 2],
+///     version: u16,
+/// }
+///
+/// struct File {
+///     header: Header,
+///     flags: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = b"HX\x02\x00\x80";
+///     let file = parse_struct!(bytes.as_ref() => File {
+///         header: Header { magic: b"HX", version: u16 @ le "____" },
+///         flags: u8 @ "__",
+///     })?;
+///     assert_eq!(file.header.version, 2);
+///     assert_eq!(file.flags, 0x80);
+///     Ok(())
+/// }
+/// ```
+///
+/// `FIELD: [STRUCT; COUNT] @ { FIELDS }` parses `COUNT` nested structs in a row into a
+/// `Vec<STRUCT>`. Like the length of `vec(LENGTH)`, `COUNT` can refer to earlier fields.
+/// The records are parsed one by one, so a large `COUNT` in malformed input results in an
+/// error at the end of the input rather than in a large allocation.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Entry {
+///     id: u8,
+///     value: u16,
+/// }
+///
+/// struct Table {
+///     count: u8,
+///     entries: Vec<Entry>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x02, 0x01, 0x10, 0x00, 0x02, 0x20, 0x00];
+///     let table = parse_struct!(bytes.as_ref() => Table {
+///         count: u8 @ "__",
+///         entries: [Entry; count] @ { id: u8 @ "__", value: u16 @ le "____" },
+///     })?;
+///     assert_eq!(table.entries.len(), 2);
+///     assert_eq!(table.entries[1].value, 0x20);
+///     Ok(())
+/// }
+/// ```
+///
+/// The generated code only refers to `std` for I/O errors. In `no_std` crates with `alloc`,
+/// an `#[io(PATH)]` attribute before the struct replaces `std::io` with a module providing
+/// `Error` (with `Error::new(ErrorKind, message)` and `Error::kind()`) and `ErrorKind` (with
+/// `InvalidData` and `UnexpectedEof`), and the reader can implement any trait in scope with
+/// a `read_exact` method. `format!` and `vec!` have to be imported from `alloc`. With
+/// `seekable`, the module also has to provide `SeekFrom` for the reader's `seek` method.
+/// `skip_to(MAGIC)` fields and the `exact` modifier call the reader's `read` method, retrying
+/// reads failing with `ErrorKind::Interrupted` like `read_exact`, so `ErrorKind` then also needs
+/// an `Interrupted` variant.
+///
+/// ```ignore
+/// use alloc::{format, vec};
+/// use my_io::Read;
+///
+/// let data = parse_struct!(reader => #[io(my_io)] Data { ... })?;
+/// ```
+///
+/// Structs or enum variants with unnamed members (`Item(A, B)`) can be used with the
+/// `Struct { 0: ..., 1: ... }` syntax.
+///
+/// This macro returns `Result` containing either the resulting struct
+/// or [`std::io::Error`](std::io::Error) if an error occurred while reading or matching the bytes.
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData)
+/// if the bytes were not matched successfully.
+///
+/// With the `counted` modifier, the macro returns the struct along with the number of bytes read,
+/// including skipped bytes and bytes read by `vec(LENGTH)` and nested structs. This is useful for
+/// advancing a cursor over back-to-back records.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Record {
+///     len: u8,
+///     data: Vec<u8>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x02, 0xAA, 0xBB, 0x00];
+///     let (record, count) = parse_struct!(counted bytes.as_ref() => Record {
+///         len: u8 @ "__",
+///         data: vec(len),
+///         _: skip(1),
+///     })?;
+///     assert_eq!(record.data, vec![0xAA, 0xBB]);
+///     assert_eq!(count, 4);
+///     Ok(())
+/// }
+/// ```
+///
+/// The reader is moved into the macro, so a reader which is used afterwards is passed as
+/// `&mut reader`, which is left positioned after the bytes of the struct. With the `threaded`
+/// modifier, the macro returns the reader after the struct instead (last, after the count of
+/// `counted`), which allows chaining parsers over readers passed by value.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Cursor, Read, Result};
+///
+/// struct Tag {
+///     kind: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let mut reader = Cursor::new([0x01, 0x02, 0x03]);
+///     let first = parse_struct!(&mut reader => Tag { kind: u8 @ "01" })?;
+///     assert_eq!(reader.position(), 1);
+///
+///     let (second, reader) = parse_struct!(threaded reader => Tag { kind: u8 @ "02" })?;
+///     let (third, count, reader) = parse_struct!(counted threaded reader => Tag {
+///         kind: u8 @ "03",
+///     })?;
+///     assert_eq!([first.kind, second.kind, third.kind], [1, 2, 3]);
+///     assert_eq!((count, reader.position()), (1, 3));
+///     Ok(())
+/// }
+/// ```
+///
+/// The fields are read in a closure which is called once, so `?` in the reader expression and
+/// in field expressions returns the error from the macro rather than from the enclosing
+/// function. The reader expression is evaluated exactly once, before the first field. Variables
+/// used by the expressions are borrowed rather than moved, and since the closure is known to be
+/// called only once, borrowed readers such as `&mut cursor` or `file.by_ref()` can be returned
+/// by `threaded`. Async blocks of `async` capture variables the same way, so a reader that
+/// the returned future must own is passed by value.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{BufReader, Cursor, Read, Result};
+///
+/// struct Tag {
+///     kind: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let mut cursor = Cursor::new([0x01, 0x02]);
+///     let (first, rest) = parse_struct!(threaded &mut cursor => Tag { kind: u8 @ "01" })?;
+///     let second = parse_struct!(rest => Tag { kind: u8 @ "02" })?;
+///     assert_eq!([first.kind, second.kind, cursor.position() as u8], [1, 2, 2]);
+///
+///     let mut file = BufReader::new(&[0x03, 0x04][..]);
+///     let (third, rest) = parse_struct!(threaded file.by_ref() => Tag { kind: u8 @ "03" })?;
+///     let fourth = parse_struct!(rest => Tag { kind: u8 @ "04" })?;
+///     assert_eq!([third.kind, fourth.kind], [3, 4]);
+///     Ok(())
+/// }
+/// ```
+///
+/// With the `exact` modifier, the input must end after the last field, which catches schemas
+/// missing fields at the end and trailing garbage. One more byte is read from readers, and
+/// getting one results in an
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) error, as do remaining
+/// bytes of `parse_slice!` slices.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Tag {
+///     kind: u8,
+/// }
+///
+/// let bytes = [0x01, 0x02];
+/// let tag = parse_struct!(exact &bytes[..1] => Tag { kind: u8 @ "01" });
+/// assert_eq!(tag.unwrap().kind, 1);
+/// let tag = parse_struct!(exact bytes.as_ref() => Tag { kind: u8 @ "01" });
+/// assert_eq!(tag.err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+/// ```
+///
+/// With the `seekable` modifier, `skip(N)` fields seek over the skipped bytes with
+/// `seek(SeekFrom::Current(N))` instead of reading them, so large reserved regions are neither
+/// read nor buffered. The reader must implement `Seek`, which has to be in scope along with `Read`.
+/// Seeking past the end of the input isn't an error, so it's only reported by the next read.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Cursor, Read, Result, Seek};
+///
+/// struct Header {
+///     magic: [u8; 2],
+///     offset: u32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let mut bytes = vec![0u8; 4096 + 6];
+///     bytes[..2].copy_from_slice(b"HX");
+///     bytes[4098..].copy_from_slice(&[0x10, 0x00, 0x00, 0x00]);
+///     let header = parse_struct!(seekable Cursor::new(bytes) => Header {
+///         magic: b"HX",
+///         _: skip(4096),
+///         offset: u32 @ le "________",
+///     })?;
+///     assert_eq!(header.offset, 0x10);
+///     Ok(())
+/// }
+/// ```
+///
+/// In `seekable` structs, any field can be preceded by `seek(OFFSET)`, which moves the reader by
+/// `OFFSET` bytes with `seek(SeekFrom::Current(OFFSET))` before the field is read. A negative
+/// offset reads bytes again, which is useful for formats interpreting the same bytes in more
+/// than one way. Seeking back past the start of the struct fails with `InvalidInput`, and offsets
+/// must fit in an `i64`. Fields read after seeking can't be written by `write_struct!`, and
+/// `parse_slice!` moves its offset into the slice without requiring `seekable`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Cursor, Read, Result, Seek};
+///
+/// struct Overlay {
+///     low: u16,
+///     high: u16,
+///     whole: u32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x02, 0x03, 0x04];
+///     let overlay = parse_struct!(seekable Cursor::new(bytes) => Overlay {
+///         low: u16 @ le "____",
+///         high: u16 @ le "____",
+///         whole: seek(-4) u32 @ le "________",
+///     })?;
+///     assert_eq!((overlay.low, overlay.high, overlay.whole), (0x0201, 0x0403, 0x04030201));
+///
+///     let overlay = parse_struct!(seekable Cursor::new(bytes) => Overlay {
+///         low: u16 @ le "____",
+///         high: u16 @ le "____",
+///         whole: seek(-6) u32 @ le "________",
+///     });
+///     assert!(overlay.is_err()); // can't seek back 6 bytes after reading 4
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::{Cursor, Read};
+///
+/// struct Overlay {
+///     low: u16,
+///     whole: u16,
+/// }
+///
+/// let overlay = parse_struct!(Cursor::new([0x01, 0x02]) => Overlay {
+///     low: u16 @ le "____",
+///     whole: seek(-2) u16 @ le "____", // fields can only seek in `seekable` structs
+/// });
+/// ```
+///
+/// `@reader(READER)` before a field switches to another reader for that field and the following
+/// ones, such as a data section which a header points into. Earlier fields are in scope, so the
+/// new reader can be built from their values. Errors of the fields are the errors of the reader
+/// they're read from. The count of bytes used by `counted` and by alignment is kept across
+/// readers, while `exact` checks that the last reader ended and `threaded` returns the last
+/// reader. In `seekable` structs, `seek(-N)` can only move back over bytes read from the current
+/// reader. Readers can only be switched between fields of the outermost struct, and not in
+/// slices, buffered structs, enums or `write_struct!`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Entry {
+///     offset: u8,
+///     len: u8,
+///     data: Vec<u8>,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let index = [0x01, 0x02];
+///     let section = [0x00, 0xAA, 0xBB, 0xCC];
+///     let entry = parse_struct!(index.as_ref() => Entry {
+///         offset: u8 @ "__",
+///         len: u8 @ "__",
+///         @reader(&section[offset as usize..])
+///         data: vec(len as usize),
+///     })?;
+///     assert_eq!(entry.data, [0xAA, 0xBB]);
+///     Ok(())
+/// }
+/// ```
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Cursor, ErrorKind, Read, Seek};
+///
+/// struct Pair {
+///     index: u8,
+///     value: u8,
+/// }
+///
+/// let section = [0xAA];
+/// let err = parse_struct!(seekable Cursor::new([0x01]) => Pair {
+///     index: u8 @ "__",
+///     @reader(Cursor::new(section))
+///     value: seek(-1) u8 @ "__",
+/// })
+/// .err()
+/// .unwrap();
+/// assert_eq!(err.kind(), ErrorKind::InvalidInput);
+/// ```
+///
+/// With the `buffered` modifier, the whole struct is read with a single `read_exact` call into
+/// a local buffer before its fields are read from memory. This requires the length of the struct
+/// to be known at compile time, so `vec(LENGTH)`, records, constants, optional fields and `..`
+/// can't be used. It avoids partial reads in the middle of a struct and reduces the number of
+/// reads from unbuffered readers such as sockets.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Frame {
+///     kind: u8,
+///     length: u16,
+///     checksum: u32,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x00, 0x10, 0xDE, 0xAD, 0xBE, 0xEF];
+///     let frame = parse_struct!(buffered bytes.as_ref() => Frame {
+///         kind: u8 @ "01",
+///         length: u16 @ be "____",
+///         checksum: u32 @ be "________",
+///     })?;
+///     assert_eq!(frame.checksum, 0xDEADBEEF);
+///     Ok(())
+/// }
+/// ```
+///
+/// Fixed-length fields are read into an array on the stack as large as the longest of them.
+/// To avoid large stack frames, a scratch buffer (`&mut [u8]`) can be given after the reader with
+/// `READER, SCRATCH =>`, which is used by the struct and all of its nested structs instead. If it's
+/// shorter than the longest field, the macro fails with `InvalidInput` before reading anything.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read, Result};
+///
+/// struct Block {
+///     id: u8,
+///     key: [u8; 32],
+/// }
+///
+/// fn main() -> Result<()> {
+///     let mut bytes = vec![0x01];
+///     bytes.extend([0xAA; 32]);
+///     let mut scratch = vec![0u8; 32];
+///     let block = parse_struct!(bytes.as_slice(), &mut scratch => Block {
+///         id: u8 @ "01",
+///         key: "[__; 32]",
+///     })?;
+///     assert_eq!(block.key, [0xAA; 32]);
+///
+///     let result = parse_struct!(bytes.as_slice(), &mut [0u8; 16] => Block {
+///         id: u8 @ "01",
+///         key: "[__; 32]",
+///     });
+///     assert_eq!(result.err().map(|err| err.kind()), Some(ErrorKind::InvalidInput));
+///     Ok(())
+/// }
+/// ```
+///
+/// With the `collect` modifier, mismatched patterns, constants and guards don't stop parsing.
+/// Each of them is recorded and the macro returns all of them as a `Vec<std::io::Error>` once
+/// the struct is read, which is useful for validating files in linters and test harnesses.
+/// Errors which prevent reading the following fields, such as I/O errors, still stop parsing and
+/// are returned after the mismatches found so far. With `#[error(TYPE)]`, each error is converted.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Header {
+///     version: u8,
+///     length: u16,
+/// }
+///
+/// let bytes = [0x02, 0xAA, 0x00, 0x10];
+/// let errors = parse_struct!(collect bytes.as_ref() => Header {
+///     version: u8 @ "01",
+///     _: "BB",
+///     length: u16 @ be "____" if length < 8,
+/// })
+/// .err()
+/// .unwrap();
+/// assert_eq!(errors.len(), 3);
+/// ```
+///
+/// With the `trace` modifier, each field is printed to stderr with `eprintln!` once its bytes
+/// are read and match, along with the offset it was read at and its bytes, or the number of
+/// bytes skipped by `_: skip(N)`, `_: align(N)`, `_: skip_to(MAGIC)` and `_: ..`. Fields of
+/// nested structs are printed with the path of the struct (`inner.a`), and fields of repeated
+/// records with `[]` after it (`entries[].id`). When parsing fails, the last printed field shows
+/// how far the input matched the schema. Tracing is only
+/// generated with the `trace` feature of this crate. Without it, `trace` is accepted but ignored,
+/// so it can be left in place and costs nothing in builds which don't enable the feature.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// struct Header {
+///     version: u8,
+///     length: u16,
+/// }
+///
+/// let bytes = [0x01, 0x00, 0x10];
+/// // parse_struct: `version` at 0: [01]
+/// // parse_struct: `length` at 1: [00, 10]
+/// let header = parse_struct!(trace bytes.as_ref() => Header {
+///     version: u8 @ "01",
+///     length: u16 @ be "____",
+/// })
+/// .unwrap();
+/// assert_eq!(header.length, 16);
+/// ```
+///
+/// With the `async` modifier, the macro returns a future and each read is awaited, so it can be
+/// used with asynchronous readers. The reads are `read_exact(...).await` calls, along with
+/// `read`, `read_to_end` and `read_until` for the fields needing them, resolved by the
+/// `AsyncReadExt` trait in scope, such as the one provided by `tokio` or `futures`. The examples
+/// below use a small stand-in trait over a slice and a minimal executor so they can run without
+/// either of them.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// # use std::future::{ready, Future, Ready};
+/// # use std::io::Result;
+/// #
+/// # /// A minimal `AsyncReadExt` over a slice, whose futures are always ready.
+/// # trait AsyncReadExt {
+/// #     fn read(&mut self, buf: &mut [u8]) -> Ready<Result<usize>>;
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>>;
+/// #     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Ready<Result<usize>>;
+/// #     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Ready<Result<usize>>;
+/// # }
+/// #
+/// # impl AsyncReadExt for &[u8] {
+/// #     fn read(&mut self, buf: &mut [u8]) -> Ready<Result<usize>> {
+/// #         ready(std::io::Read::read(self, buf))
+/// #     }
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>> {
+/// #         ready(std::io::Read::read_exact(self, buf))
+/// #     }
+/// #     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Ready<Result<usize>> {
+/// #         ready(std::io::Read::read_to_end(self, buf))
+/// #     }
+/// #     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Ready<Result<usize>> {
+/// #         ready(std::io::BufRead::read_until(self, byte, buf))
+/// #     }
+/// # }
+/// #
+/// # /// Polls a future to completion.
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+/// #     fn raw() -> RawWaker {
+/// #         RawWaker::new(std::ptr::null(), &VTABLE)
+/// #     }
+/// #     static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+/// #     let waker = unsafe { Waker::from_raw(raw()) };
+/// #     let mut context = Context::from_waker(&waker);
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+/// #             return output;
+/// #         }
+/// #     }
+/// # }
+///
+/// struct Header {
+///     length: u16,
+///     name: String,
+///     line: Vec<u8>,
+/// }
+///
+/// let stream: &[u8] = b"..HX\x00\x10hex\x00a line\n";
+/// let header = block_on(parse_struct!(async exact stream => Header {
+///     _: skip_to(b"HX"),
+///     length: u16 @ be "____",
+///     name: String @ cstr,
+///     line: until(b'\n'),
+/// }))
+/// .unwrap();
+/// assert_eq!((header.length, header.name.as_str()), (16, "hex"));
+/// assert_eq!(header.line, b"a line");
+/// ```
+///
+/// The other modifiers and `rest` fields work the same way when reading asynchronously.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// # use std::future::{ready, Future, Ready};
+/// # use std::io::Result;
+/// #
+/// # /// A minimal `AsyncReadExt` over a slice, whose futures are always ready.
+/// # trait AsyncReadExt {
+/// #     fn read(&mut self, buf: &mut [u8]) -> Ready<Result<usize>>;
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>>;
+/// #     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Ready<Result<usize>>;
+/// #     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Ready<Result<usize>>;
+/// # }
+/// #
+/// # impl AsyncReadExt for &[u8] {
+/// #     fn read(&mut self, buf: &mut [u8]) -> Ready<Result<usize>> {
+/// #         ready(std::io::Read::read(self, buf))
+/// #     }
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>> {
+/// #         ready(std::io::Read::read_exact(self, buf))
+/// #     }
+/// #     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Ready<Result<usize>> {
+/// #         ready(std::io::Read::read_to_end(self, buf))
+/// #     }
+/// #     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Ready<Result<usize>> {
+/// #         ready(std::io::BufRead::read_until(self, byte, buf))
+/// #     }
+/// # }
+/// #
+/// # /// Polls a future to completion.
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+/// #     fn raw() -> RawWaker {
+/// #         RawWaker::new(std::ptr::null(), &VTABLE)
+/// #     }
+/// #     static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+/// #     let waker = unsafe { Waker::from_raw(raw()) };
+/// #     let mut context = Context::from_waker(&waker);
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+/// #             return output;
+/// #         }
+/// #     }
+/// # }
+///
+/// struct Packet {
+///     kind: u8,
+///     payload: Vec<u8>,
+/// }
+///
+/// let stream: &[u8] = &[0x02, 0xAA, 0xBB];
+/// let errors = block_on(parse_struct!(async collect stream => Packet {
+///     kind: u8 @ "01",
+///     payload: rest,
+/// }))
+/// .err()
+/// .unwrap();
+/// assert_eq!(errors.len(), 1);
+///
+/// let stream: &[u8] = &[0x01, 0xAA, 0xBB];
+/// let packet = block_on(parse_struct!(async stream => Packet {
+///     kind: u8 @ "01",
+///     payload: rest,
+/// }))
+/// .unwrap();
+/// assert_eq!(packet.payload, vec![0xAA, 0xBB]);
+/// ```
+///
+/// A different error type can be given with an `#[error(TYPE)]` attribute before the struct,
+/// in which case errors are converted with its `From<std::io::Error>` implementation.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// #[derive(Debug)]
+/// enum MyError {
+///     Io(std::io::Error),
+/// }
+///
+/// impl From<std::io::Error> for MyError {
+///     fn from(err: std::io::Error) -> Self {
+///         MyError::Io(err)
+///     }
+/// }
+///
+/// struct Data {
+///     a: [u8; 2],
+/// }
+///
+/// fn main() -> Result<(), MyError> {
+///     let bytes = [0x01, 0x02];
+///     let data = parse_struct!(bytes.as_ref() => #[error(MyError)] Data {
+///         a: "01__",
+///     })?;
+///     assert_eq!(data.a, [0x01, 0x02]);
+///     Ok(())
+/// }
+/// ```
 ///
-/// const BYTES: [u8; 3] = hex!("DEAD AF");
+/// Fields can be described with doc comments or `#[note("...")]` attributes, which makes
+/// long schemas easier to read. The description is included in the error message when the
+/// bytes of the field don't match its pattern.
 ///
-/// fn main() {
-///     assert_eq!(BYTES, [0xDE, 0xAD, 0xAF]);
-///     assert_eq!(hex!("aA aa aA Aa aa"), [0xAA; 5]);
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
 ///
-///     match [1, 2, 3, 4] {
-///         hex!("AABBCCDD") => panic!("bytes don't match at all"),
-///         hex!("01__FF__") => panic!("[1, _, 0xFF, _] does not match"),
-///         hex!("01..04") => println!("[1, .., 4] would match"),
-///         hex!("..") => unreachable!("[..] would match"),
+/// struct File {
+///     magic: [u8; 2],
+///     version: u8,
+/// }
+///
+/// let bytes = [0x4D, 0x5A, 0x01];
+/// let err = parse_struct!(bytes.as_ref() => File {
+///     /// magic header
+///     magic: "7D2B",
+///     #[note("format version")]
+///     version: u8 @ "01",
+/// })
+/// .err()
+/// .unwrap();
+/// assert_eq!(
+///     err.to_string(),
+///     "expected `[0x7D, 0x2B]` for magic header (field `magic`), got `[4D, 5A]`",
+/// );
+/// ```
+///
+/// Fields are read in the order they're given. When it differs from the order of the input,
+/// such as when matching an existing struct definition, each field can be given its position
+/// in the input with `#[order(N)]`. Either all fields of a struct or none of them have an order,
+/// and fields are read by ascending order, so later fields can still refer to the ones read
+/// before them. `write_struct!` writes fields in the same order.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
+///
+/// struct Message {
+///     body: Vec<u8>,
+///     length: u8,
+///     kind: u8,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x02, 0xAA, 0xBB];
+///     let message = parse_struct!(bytes.as_ref() => Message {
+///         #[order(2)]
+///         body: vec(length),
+///         #[order(1)]
+///         length: u8 @ "__",
+///         #[order(0)]
+///         kind: u8 @ "01",
+///     })?;
+///     assert_eq!(message.body, vec![0xAA, 0xBB]);
+///     Ok(())
+/// }
+/// ```
+///
+/// Mismatched bytes are reported with a formatted message by default. To inspect them
+/// programmatically, a `#[mismatch(TYPE)]` attribute names a struct with the fields
+/// `field: &'static str`, `expected: &'static str` and `actual: Vec<u8>`, which is created on
+/// a mismatch and converted into `std::io::Error` with `From`. Since `hex_magic` only exports
+/// macros, the type is defined by the caller. Wrapping it in the `std::io::Error` allows it to be
+/// recovered with `get_ref` and `downcast_ref`.
+///
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{self, Read};
+///
+/// #[derive(Debug)]
+/// struct ParseError {
+///     field: &'static str,
+///     expected: &'static str,
+///     actual: Vec<u8>,
+/// }
+///
+/// impl std::fmt::Display for ParseError {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "expected `{}` for `{}`, got {:02X?}", self.expected, self.field, self.actual)
+///     }
+/// }
+///
+/// impl std::error::Error for ParseError {}
+///
+/// impl From<ParseError> for io::Error {
+///     fn from(err: ParseError) -> Self {
+///         io::Error::new(io::ErrorKind::InvalidData, err)
 ///     }
 /// }
+///
+/// struct Data {
+///     magic: [u8; 2],
+/// }
+///
+/// let bytes = [0x01, 0x02];
+/// let err = parse_struct!(bytes.as_ref() => #[mismatch(ParseError)] Data {
+///     magic: "7D2B",
+/// })
+/// .err()
+/// .unwrap();
+/// let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+/// assert_eq!(err.field, "magic");
+/// assert_eq!(err.actual, vec![0x01, 0x02]);
 /// ```
-#[proc_macro]
-pub fn hex(stream: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(stream as HexString);
-    TokenStream::from(quote!(#input))
-}
-
-/// Macro for parsing bytes from [`Read`](std::io::Read) readers into structs
-/// with the ability to skip padding bytes.
 ///
-/// # Syntax
+/// Mismatched bytes, false guards and failed validations result in
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData) errors by default.
+/// `#[error_kind(KIND)]` before the struct or a field sets another
+/// [`ErrorKind`](std::io::ErrorKind) variant, with the one of a field taking precedence and
+/// applying to the fields of nested structs.
 ///
 /// ```
-/// parse_struct!(READER => STRUCT {
-///     ...
-///     FIELD: [BINDING @] BYTE_PATTERN [=> EXPRESSION],
-///     ...
+/// use hex_magic::parse_struct;
+/// use std::io::{ErrorKind, Read};
+///
+/// struct Data {
+///     magic: [u8; 2],
+///     version: u8,
+/// }
+///
+/// let bytes = [0x7D, 0x2B, 0x02];
+/// let err = parse_struct!(bytes.as_ref() => #[error_kind(Unsupported)] Data {
+///     #[error_kind(InvalidData)]
+///     magic: "7D2B",
+///     version: u8 @ "01",
 /// })
+/// .err()
+/// .unwrap();
+/// assert_eq!(err.kind(), ErrorKind::Unsupported);
 /// ```
 ///
-/// First, the macro expects a reader or an expression the result of which would be a reader.
-/// The reader is followed by `=>` and then by a modified form of struct instantiation.
+/// For quick parses, a tuple type can be given in place of the struct. Its fields are named by
+/// their index and take the type of the corresponding element, so typed elements only need the
+/// byte order and pattern. Every element must be given exactly once, in any order.
 ///
-/// The basic syntax of struct instantiation takes the form of `FIELD: BYTE_PATTERN`. This will assign
-/// the read bytes (`[u8; N]`) to the given field if it matches the pattern.
-/// For more advanced scenarios, such as for converting the bytes to other types,
-/// bindings and expressions can be used:
-/// `FIELD: BINDING @ BYTE_PATTERN => EXPRESSION`.
-/// In this case, the result of `EXPRESSION` will be assigned to `FIELD`.
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::{Read, Result};
 ///
-/// A special `_` field is available for matching against bytes without including them in the
-/// struct. `_` fields can be specified multiple times and
-/// can be used for skipping padding bytes or for matching against bytes without including them in
-/// the struct. Bindings and expressions can be used with these fields as well but expressions must
-/// evaluate to ().
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x00, 0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x00, 0x00, 0x02];
+///     let (version, magic, length) = parse_struct!(bytes.as_ref() => (u16, [u8; 4], u32) {
+///         0: le "____",
+///         1: "________",
+///         2: be "________",
+///     })?;
+///     assert_eq!(version, 1);
+///     assert_eq!(magic, [0xDE, 0xAD, 0xBE, 0xEF]);
+///     assert_eq!(length, 2);
+///     Ok(())
+/// }
+/// ```
 ///
-/// Patterns can be any of:
-/// - `[1, 2, 3, _, 5]` - standard byte array patterns
-/// - `b"byte string!"` - byte strings
-/// - `"FF00FF 00FF00"` - hex strings usable with the [`hex!`](hex!) macro
+/// ```compile_fail
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
 ///
-/// Patterns can include `_` but not `..` wildcards since the length of the pattern is
-/// used to determine the amount of bytes to read.
+/// let bytes = [0x01, 0x00];
+/// let pair = parse_struct!(bytes.as_ref() => (u8, u8) {
+///     0: "01",
+/// }); // missing field `1` of the tuple
+/// ```
 ///
-/// Structs or enum variants with unnamed members (`Item(A, B)`) can be used with the
-/// `Struct { 0: ..., 1: ... }` syntax.
+/// A struct without fields reads nothing: the reader is left untouched and the struct is
+/// returned right away, so unit-like marker structs can be parsed like any other.
 ///
-/// This macro returns `Result` containing either the resulting struct
-/// or [`std::io::Error`](std::io::Error) if an error occurred while reading or matching the bytes.
-/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData)
-/// if the bytes were not matched successfully.
+/// ```
+/// use hex_magic::parse_struct;
+/// use std::io::Read;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Marker {}
+///
+/// let bytes = [0x01, 0x02];
+/// let mut reader = bytes.as_ref();
+/// let marker = parse_struct!(&mut reader => Marker {});
+/// assert_eq!(marker.unwrap(), Marker {});
+/// assert_eq!(reader, [0x01, 0x02]);
+/// ```
 ///
 /// # Example
 ///
@@ -155,6 +2663,14 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///         b: buf @ "AABB ____" => u32::from_le_bytes(*buf),
 ///     })?;
 ///     println!("{:X?}", data); // Data { a: [1, 2], b: DDCCBBAA }
+///
+///     let data = parse_struct!(bytes.as_ref() => Data {
+///         _: skip(4),
+///         a: "01 02",
+///         _: skip(1),
+///         b: buf @ "____ ____" => u32::from_be_bytes(*buf),
+///     })?;
+///     assert_eq!(data.b, 0xAABBCCDD);
 ///     Ok(())
 /// }
 /// ```
@@ -163,22 +2679,31 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///
 /// This macro would be parsed into a closure which is instantly called so that any
 /// potential errors caused by `Read` can be handled explicitly by the user.
+/// The generated code never panics, whatever the input: conversions which can't fail given the
+/// lengths checked beforehand still result in `InvalidData` errors instead of unwrapping, so
+/// untrusted data can be parsed without risking a denial of service.
 ///
 /// The macro in the example above would be parsed into the following code
 /// (internal variable names prefixed with `_` changed for clarity):
 ///
-/// ```
+/// ```ignore
 /// (|| {
 ///     use std::convert::TryInto;
 ///     #[allow(non_snake_case)]
 ///     let mut _READER = bytes.as_ref();
 ///     #[allow(non_snake_case)]
 ///     let mut _ARRAY: [u8; 4usize] = [0; 4usize]; // length of the longest pattern
+///     #[allow(non_snake_case, unused_mut)]
+///     let mut _COUNT: usize = 0; // number of bytes read, returned by `counted`
 ///     let _: () = {
 ///         _READER.read_exact(&mut _ARRAY[0..3usize])?;
+///         _COUNT += 3usize;
 ///         #[allow(non_snake_case)]
-///         let _BUFFER: &[u8; 3usize] = _ARRAY[0..3usize].try_into().unwrap();
-///         #[allow(dead_code)]
+///         let _BUFFER: &[u8; 3usize] = (&_ARRAY[0..3usize]).try_into().map_err(|_| {
+///             // never fails, but results in an error rather than a panic
+///             std::io::Error::new(std::io::ErrorKind::InvalidData, "...")
+///         })?;
+///         #[allow(dead_code, unreachable_patterns)]
 ///         match _BUFFER {
 ///             [72u8, 69u8, 88u8] => (), // b"HEX"
 ///             _ => {
@@ -191,9 +2716,12 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///     };
 ///     let _: () = {
 ///         _READER.read_exact(&mut _ARRAY[0..1usize])?;
+///         _COUNT += 1usize;
 ///         #[allow(non_snake_case)]
-///         let _BUFFER: &[u8; 1usize] = _ARRAY[0..1usize].try_into().unwrap();
-///         #[allow(dead_code)]
+///         let _BUFFER: &[u8; 1usize] = (&_ARRAY[0..1usize]).try_into().map_err(|_| {
+///             std::io::Error::new(std::io::ErrorKind::InvalidData, "...")
+///         })?;
+///         #[allow(dead_code, unreachable_patterns)]
 ///         match _BUFFER {
 ///             [0] => (),
 ///             _ => {
@@ -204,11 +2732,14 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///             }
 ///         }
 ///     };
-///     let _a = {
+///     let a = {
 ///         _READER.read_exact(&mut _ARRAY[0..2usize])?;
+///         _COUNT += 2usize;
 ///         #[allow(non_snake_case)]
-///         let _BUFFER: &[u8; 2usize] = _ARRAY[0..2usize].try_into().unwrap();
-///         #[allow(dead_code)]
+///         let _BUFFER: &[u8; 2usize] = (&_ARRAY[0..2usize]).try_into().map_err(|_| {
+///             std::io::Error::new(std::io::ErrorKind::InvalidData, "...")
+///         })?;
+///         #[allow(dead_code, unreachable_patterns)]
 ///         match _BUFFER {
 ///             [0x01, _] => (),
 ///             _ => {
@@ -222,9 +2753,12 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///     };
 ///     let _: () = {
 ///         _READER.read_exact(&mut _ARRAY[0..1usize])?;
+///         _COUNT += 1usize;
 ///         #[allow(non_snake_case)]
-///         let _BUFFER: &[u8; 1usize] = _ARRAY[0..1usize].try_into().unwrap();
-///         #[allow(dead_code)]
+///         let _BUFFER: &[u8; 1usize] = (&_ARRAY[0..1usize]).try_into().map_err(|_| {
+///             std::io::Error::new(std::io::ErrorKind::InvalidData, "...")
+///         })?;
+///         #[allow(dead_code, unreachable_patterns)]
 ///         match _BUFFER {
 ///             [0u8] => (),
 ///             _ => {
@@ -235,11 +2769,14 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///             }
 ///         }
 ///     };
-///     let _b = {
+///     let b = {
 ///         _READER.read_exact(&mut _ARRAY[0..4usize])?;
+///         _COUNT += 4usize;
 ///         #[allow(non_snake_case)]
-///         let buf: &[u8; 4usize] = _ARRAY[0..4usize].try_into().unwrap(); // assign binding
-///         #[allow(dead_code)]
+///         let buf: &[u8; 4usize] = (&_ARRAY[0..4usize]).try_into().map_err(|_| {
+///             std::io::Error::new(std::io::ErrorKind::InvalidData, "...")
+///         })?; // assign binding
+///         #[allow(dead_code, unreachable_patterns)]
 ///         match buf {
 ///             [170u8, 187u8, _, _] => (), // "AABB ____"
 ///             _ => {
@@ -251,7 +2788,8 @@ pub fn hex(stream: TokenStream) -> TokenStream {
 ///         }
 ///         u32::from_le_bytes(*buf) // provided expression
 ///     };
-///     Ok(Data { a: _a, b: _b }) // `_` fields are not included in the resulting struct
+///     let _ = _COUNT;
+///     Result::<_, std::io::Error>::Ok(Data { a: a, b: b }) // `_` fields are not included
 /// })()
 /// ```
 #[proc_macro]
@@ -259,3 +2797,379 @@ pub fn parse_struct(stream: TokenStream) -> TokenStream {
     let input = parse_macro_input!(stream as HexStruct);
     TokenStream::from(quote!(#input))
 }
+
+/// Macro for parsing structs from byte slices without copying their bytes.
+///
+/// `parse_slice!` takes the same input as [`parse_struct!`](parse_struct!) with a `&[u8]` in
+/// place of the reader. Instead of being copied into arrays, fields without types or expressions
+/// are `&[u8; N]` references into the slice, and `vec(LENGTH)`, `until(BYTE)` and `cstr`
+/// fields are `&[u8]` sub-slices, so the struct borrows from the slice. This avoids copying
+/// large fields of in-memory data, such as memory-mapped files.
+///
+/// Typed fields, bindings, expressions, nested structs and records work as in `parse_struct!`.
+/// Reading past the end of the slice results in an
+/// [`std::io::ErrorKind::UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error. With
+/// `counted`, the number of bytes read is the offset of the rest of the slice, which is returned
+/// by `threaded`.
+/// Slices are never read asynchronously and skipped bytes are never read, so `async`
+/// and `seekable` can't be used, while `seek(OFFSET)` fields move the offset into the slice.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::parse_slice;
+/// use std::io::Result;
+///
+/// struct Chunk<'a> {
+///     tag: &'a [u8; 4],
+///     len: u16,
+///     data: &'a [u8],
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = b"DATA\x03\x00abcREST";
+///     let (chunk, count) = parse_slice!(counted bytes.as_ref() => Chunk {
+///         tag: "____ ____",
+///         len: u16 @ le "____",
+///         data: vec(len),
+///     })?;
+///     assert_eq!(chunk.tag, b"DATA");
+///     assert_eq!(chunk.data, b"abc");
+///     assert_eq!(&bytes[count..], b"REST");
+///     Ok(())
+/// }
+/// ```
+#[proc_macro]
+pub fn parse_slice(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexSlice);
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro for parsing enums from [`Read`](std::io::Read) readers, selecting the variant
+/// by a leading tag.
+///
+/// # Syntax
+///
+/// ```text
+/// parse_enum!([async] READER => [#[io(PATH)]] [BINDING @] ENUM {
+///     [FIELD: BYTE_PATTERN, ...]
+///     ...
+///     TAG => VARIANT { FIELDS },
+///     ...
+/// })
+/// ```
+///
+/// The tag is read first and matched against the `TAG` patterns in order, then the fields of
+/// the selected variant are parsed like the fields of [`parse_struct!`](parse_struct!).
+/// `TAG` is either a single byte given as an integer literal (`0x01`) or a byte pattern of a
+/// fixed number of bytes (`"01"`, `b"PI"`, `[0x01, _]`), and all tags must have the same
+/// length. Patterns reading no bytes or a variable number of them, such as constants, nested
+/// structs, `..` or `vec(N)`, can't be tags.
+/// Unit variants are written with empty braces (`0x01 => Ping {}`).
+///
+/// Like with `parse_struct!`, `async` before the reader returns a future awaiting each read, and
+/// `#[io(PATH)]` before the enum replaces `std::io` with another module.
+///
+/// ```
+/// use hex_magic::parse_enum;
+/// # use std::future::{ready, Future, Ready};
+/// # use std::io::Result;
+/// #
+/// # /// A minimal `AsyncReadExt` over a slice, whose futures are always ready.
+/// # trait AsyncReadExt {
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>>;
+/// # }
+/// #
+/// # impl AsyncReadExt for &[u8] {
+/// #     fn read_exact(&mut self, buf: &mut [u8]) -> Ready<Result<()>> {
+/// #         ready(std::io::Read::read_exact(self, buf))
+/// #     }
+/// # }
+/// #
+/// # /// Polls a future to completion.
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+/// #     fn raw() -> RawWaker {
+/// #         RawWaker::new(std::ptr::null(), &VTABLE)
+/// #     }
+/// #     static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+/// #     let waker = unsafe { Waker::from_raw(raw()) };
+/// #     let mut context = Context::from_waker(&waker);
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+/// #             return output;
+/// #         }
+/// #     }
+/// # }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Message {
+///     Ping,
+///     Pong { seq: u32 },
+/// }
+///
+/// let stream: &[u8] = &[0x02, 0x2A, 0x00, 0x00, 0x00];
+/// let message = block_on(parse_enum!(async stream => Message {
+///     0x01 => Ping {},
+///     0x02 => Pong { seq: u32 @ le "________" },
+/// }))
+/// .unwrap();
+/// assert_eq!(message, Message::Pong { seq: 42 });
+/// ```
+///
+/// This macro returns `Result<Enum, std::io::Error>`, with
+/// [`std::io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData)
+/// if no tag matches.
+///
+/// Fields given before the variants are read after the tag and before the variant is
+/// selected, such as the length of a tag-length-value record. Like the fields of
+/// `parse_struct!`, they're bound to their names, which are in scope in the fields of all
+/// variants. `BINDING @` before the enum binds the bytes of the tag as well.
+///
+/// ```
+/// use hex_magic::parse_enum;
+/// use std::io::{Read, Result};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Record {
+///     Text { text: String },
+///     Raw { kind: u8, bytes: Vec<u8> },
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x01, 0x02, 0x00, b'h', b'i'];
+///     let record = parse_enum!(bytes.as_ref() => tag @ Record {
+///         length: u16 @ le "____",
+///         0x01 => Text { text: String @ vec(length) },
+///         [_] => Raw { kind: "" => tag[0], bytes: vec(length) },
+///     })?;
+///     assert_eq!(record, Record::Text { text: "hi".to_string() });
+///
+///     let bytes = [0x07, 0x01, 0x00, 0xFF];
+///     let record = parse_enum!(bytes.as_ref() => tag @ Record {
+///         length: u16 @ le "____",
+///         0x01 => Text { text: String @ vec(length) },
+///         [_] => Raw { kind: "" => tag[0], bytes: vec(length) },
+///     })?;
+///     assert_eq!(record, Record::Raw { kind: 7, bytes: vec![0xFF] });
+///     Ok(())
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::parse_enum;
+/// use std::io::{Read, Result};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Message {
+///     Ping,
+///     Pong { seq: u32 },
+/// }
+///
+/// fn main() -> Result<()> {
+///     let bytes = [0x02, 0x2A, 0x00, 0x00, 0x00];
+///     let message = parse_enum!(bytes.as_ref() => Message {
+///         0x01 => Ping {},
+///         0x02 => Pong { seq: u32 @ le "________" },
+///     })?;
+///     assert_eq!(message, Message::Pong { seq: 42 });
+///
+///     let error = parse_enum!([0x03].as_ref() => Message {
+///         0x01 => Ping {},
+///     });
+///     assert!(error.is_err());
+///     Ok(())
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::parse_enum;
+/// use std::io::Read;
+///
+/// enum Message {
+///     Ping,
+/// }
+///
+/// const PING: [u8; 1] = [0x01];
+///
+/// let message = parse_enum!([0x01].as_ref() => Message {
+///     PING => Ping {}, // expected a tag of a fixed number of bytes
+/// });
+/// ```
+#[proc_macro]
+pub fn parse_enum(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as HexEnum);
+    TokenStream::from(quote!(#input))
+}
+
+/// Macro for writing structs to [`Write`](std::io::Write) writers, the inverse of
+/// [`parse_struct!`](parse_struct!).
+///
+/// # Syntax
+///
+/// ```text
+/// write_struct!(WRITER, VALUE => STRUCT {
+///     ...
+///     FIELD: BYTE_PATTERN,
+///     FIELD: TYPE @ [le|be|nul] BYTE_PATTERN,
+///     _: BYTE_PATTERN,
+///     ...
+/// })
+/// ```
+///
+/// The fields use the same syntax as [`parse_struct!`](parse_struct!), so the same
+/// schema can be used for reading and writing a struct. `VALUE` is destructured as `STRUCT`
+/// (usually by reference) and its fields are written in order:
+///
+/// - `_` fields write the bytes of their pattern, which can't contain wildcards.
+///   `_: skip(N)` writes `N` null bytes and `_: skip_to(MAGIC)` writes `MAGIC`.
+/// - byte array fields are checked against their pattern (or constant) and written as they are.
+/// - typed fields are converted with `to_le_bytes` or `to_be_bytes`, where the byte order can
+///   default to the one given by an `#[endian(le|be)]` attribute before the struct. `String`
+///   fields must have the exact length of their pattern, or at most that length with `nul`, in
+///   which case they're padded with null bytes. Optional fields are only written if they're `Some`.
+/// - `vec(LENGTH)` and `rest` fields are written as they are.
+/// - `until(BYTE)` and `cstr` fields are written followed by the delimiter, which they can't
+///   contain.
+/// - nested structs are written field by field.
+/// - `bits { ... } @` fields are written from the bytes of their member, so `_` fields can't be
+///   split into bit fields.
+/// - `virtual` fields aren't written.
+///
+/// Fields with expressions can't be written since expressions can't be reversed, except for
+/// `virtual` fields.
+///
+/// This macro returns `Result<(), std::io::Error>`, with
+/// [`std::io::ErrorKind::InvalidInput`](std::io::ErrorKind::InvalidInput)
+/// if a value doesn't match its pattern.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::{parse_struct, write_struct};
+/// use std::io::{Read, Result, Write};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Data {
+///     a: [u8; 2],
+///     b: u32,
+///     name: String,
+/// }
+///
+/// fn main() -> Result<()> {
+///     let data = Data { a: [0x01, 0x02], b: 0xAABBCCDD, name: "HEX".to_string() };
+///
+///     let mut bytes = vec![];
+///     write_struct!(&mut bytes, &data => Data {
+///         _: b"HEX",
+///         a: [0x01, _],
+///         _: skip(1),
+///         b: u32 @ le "________",
+///         name: String @ nul "________",
+///     })?;
+///     assert_eq!(bytes, b"HEX\x01\x02\0\xDD\xCC\xBB\xAAHEX\0");
+///
+///     let parsed = parse_struct!(bytes.as_slice() => Data {
+///         _: b"HEX",
+///         a: [0x01, _],
+///         _: skip(1),
+///         b: u32 @ le "________",
+///         name: String @ nul "________",
+///     })?;
+///     assert_eq!(parsed, data);
+///     Ok(())
+/// }
+/// ```
+#[proc_macro]
+pub fn write_struct(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as WriteStruct);
+    TokenStream::from(quote!(#input))
+}
+
+/// Derive macro implementing a `parse` method which reads the struct with
+/// [`parse_struct!`](parse_struct!), for types parsed in many places.
+///
+/// The bytes of each field are given by a `#[hex(BYTE_PATTERN)]` attribute, which takes the
+/// byte patterns of `parse_struct!` such as hex strings, byte strings and `vec(LENGTH)`. Fields
+/// of primitive number types, arrays of them, `String` and `Option` of them are converted as
+/// typed fields, so `le`, `be` and `nul` can be given at the start of the pattern.
+///
+/// The attributes of `parse_struct!` fields are given inside `#[hex(...)]` as well, so they
+/// don't collide with the helper attributes of other derives: `#[hex(le)]`, `#[hex(be)]`,
+/// `#[hex(note("..."))]`, `#[hex(order(N))]`, `#[hex(bom)]` and `#[hex(error_kind(KIND))]` work
+/// as the corresponding attributes in `parse_struct!`, and `#[hex(skip(N))]` skips `N` bytes
+/// before the field. Several of them can be combined in one attribute (`#[hex(skip(2), le)]`),
+/// and an attribute which doesn't only contain options is the byte pattern of the field. Doc
+/// comments are used as notes. A `#[hex(endian(le|be))]` attribute on the struct sets the default
+/// byte order of its fields, and a `#[hex(error_kind(KIND))]` attribute the kind of their mismatch
+/// errors. Members such as `PhantomData` which aren't read from the input take `#[hex(virtual)]`.
+///
+/// The generated method is `pub fn parse<R: Read>(reader: R) -> std::io::Result<Self>`, or
+/// takes a `R: BufRead` if fields are read with `until(BYTE)` or `cstr`.
+///
+/// # Example
+///
+/// ```
+/// use hex_magic::ParseStruct;
+///
+/// #[derive(ParseStruct)]
+/// struct Header {
+///     #[hex(b"HX")]
+///     magic: [u8; 2],
+///     #[hex(le "____")]
+///     length: u16,
+///     /// format version
+///     #[hex(skip(2))]
+///     #[hex("01")]
+///     version: u8,
+///     #[hex(vec(length))]
+///     body: Vec<u8>,
+/// }
+///
+/// fn main() -> std::io::Result<()> {
+///     let bytes = [0x48, 0x58, 0x02, 0x00, 0x00, 0x00, 0x01, 0xAA, 0xBB];
+///     let header = Header::parse(bytes.as_ref())?;
+///     assert_eq!(header.length, 2);
+///     assert_eq!(header.body, vec![0xAA, 0xBB]);
+///     Ok(())
+/// }
+/// ```
+///
+/// ```
+/// use hex_magic::ParseStruct;
+///
+/// #[derive(Debug, ParseStruct)]
+/// #[hex(endian(le), error_kind(Unsupported))]
+/// struct Entry {
+///     #[hex("____")]
+///     id: u16,
+///     #[hex(skip(1), be)]
+///     #[hex("____")]
+///     size: u16,
+///     #[hex(cstr)]
+///     name: Vec<u8>,
+/// }
+///
+/// let entry = Entry::parse(&b"\x01\x00\xFF\x00\x10hex\x00"[..]).unwrap();
+/// assert_eq!((entry.id, entry.size), (1, 16));
+/// assert_eq!(entry.name, b"hex");
+/// ```
+///
+/// ```compile_fail
+/// use hex_magic::ParseStruct;
+///
+/// #[derive(ParseStruct)]
+/// struct Data {
+///     #[le] // cannot find attribute `le` in this scope
+///     #[hex("____")]
+///     length: u16,
+/// }
+/// ```
+#[proc_macro_derive(ParseStruct, attributes(hex))]
+pub fn derive_parse_struct(stream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(stream as DeriveParseStruct);
+    TokenStream::from(quote!(#input))
+}
@@ -0,0 +1,180 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+
+use syn::{
+    braced,
+    punctuated::Punctuated,
+    token::{Brace, Colon, Comma},
+    Ident, LitInt, Result, Token,
+};
+
+use super::internal_ident;
+
+mod kw {
+    syn::custom_keyword!(bits);
+    syn::custom_keyword!(msb);
+    syn::custom_keyword!(lsb);
+}
+
+/// Order in which bit fields are taken from the bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BitOrder {
+    /// The first field is in the most significant bits of the first byte.
+    Msb,
+    /// The first field is in the least significant bits of the first byte.
+    Lsb,
+}
+
+/// A single named range of bits: `NAME: WIDTH`.
+#[derive(Debug)]
+struct BitField {
+    /// Name the bits are bound to, or `None` for unused bits given by `_`.
+    ident: Option<Ident>,
+    colon: Colon,
+    width: LitInt,
+}
+
+impl Parse for BitField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident = match input.parse::<Option<Token![_]>>()? {
+            Some(_) => None,
+            None => Some(input.parse()?),
+        };
+        Ok(Self {
+            ident,
+            colon: input.parse()?,
+            width: input.parse()?,
+        })
+    }
+}
+
+/// Bytes of a field split into bit fields: `bits [msb|lsb] { NAME: WIDTH, ... } @`.
+#[derive(Debug)]
+pub struct BitFields {
+    bits: kw::bits,
+    order: BitOrder,
+    brace: Brace,
+    fields: Punctuated<BitField, Comma>,
+}
+
+impl BitFields {
+    /// Returns `true` if the input starts with bit fields, rather than with a nested struct
+    /// named `bits`.
+    pub fn peek(input: ParseStream) -> bool {
+        input.peek(kw::bits) && (input.peek2(Brace) || input.peek2(kw::msb) || input.peek2(kw::lsb))
+    }
+
+    pub fn span(&self) -> Span {
+        self.bits.span
+    }
+
+    /// Checks that the widths of the bit fields add up to the given number of bytes.
+    pub fn check_len(&self, len: usize) -> Result<()> {
+        if len == 0 || len > 16 {
+            return Err(syn::Error::new(
+                self.bits.span,
+                format!("bit fields can be split from 1 to 16 bytes, found {}", len),
+            ));
+        }
+        let mut total = 0;
+        for field in &self.fields {
+            match field.width.base10_parse::<usize>()? {
+                width @ 1..=128 => total += width,
+                _ => {
+                    return Err(syn::Error::new(
+                        field.width.span(),
+                        "bit fields must be 1 to 128 bits wide",
+                    ))
+                }
+            }
+        }
+        if total != len * 8 {
+            return Err(syn::Error::new(
+                self.brace.span,
+                format!(
+                    "expected bit fields adding up to {} bits, found {}.\n\
+                     help: name unused bits `_`",
+                    len * 8,
+                    total
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the bit fields as a tuple pattern, with `_` for unused bits.
+    pub fn pattern(&self) -> TokenStream {
+        let names = self.fields.iter().map(|field| match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => quote_spanned!(field.colon.span=> _),
+        });
+        quote!((#(#names,)*))
+    }
+
+    /// Extracts the bit fields from the byte array in `buffer` into a tuple of the smallest
+    /// unsigned integers holding them.
+    pub fn extract(&self, buffer: &Ident) -> TokenStream {
+        let bits_ident = internal_ident("BITS", self.bits.span);
+        let bytes = match self.order {
+            BitOrder::Msb => quote!(#buffer.iter()),
+            BitOrder::Lsb => quote!(#buffer.iter().rev()),
+        };
+        let total: usize = self
+            .fields
+            .iter()
+            .map(|field| field.width.base10_parse::<usize>().unwrap())
+            .sum();
+        let mut start = 0;
+        let values = self.fields.iter().map(|field| {
+            let width = field.width.base10_parse::<usize>().unwrap();
+            let shift = match self.order {
+                BitOrder::Msb => total - start - width,
+                BitOrder::Lsb => start,
+            };
+            start += width;
+            let mask = u128::MAX >> (128 - width);
+            let ty = match width {
+                1..=8 => quote!(u8),
+                9..=16 => quote!(u16),
+                17..=32 => quote!(u32),
+                33..=64 => quote!(u64),
+                _ => quote!(u128),
+            };
+            quote_spanned!(field.width.span()=> (#bits_ident >> #shift & #mask) as #ty)
+        });
+        quote!({
+            #[allow(non_snake_case)]
+            let #bits_ident = #bytes.fold(0u128, |bits, byte| bits << 8 | *byte as u128);
+            (#(#values,)*)
+        })
+    }
+}
+
+impl Parse for BitFields {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let bits = input.parse()?;
+        let order = if input.peek(kw::lsb) {
+            input.parse::<kw::lsb>()?;
+            BitOrder::Lsb
+        } else {
+            input.parse::<Option<kw::msb>>()?;
+            BitOrder::Msb
+        };
+        let content;
+        let brace = braced!(content in input);
+        let fields = content.parse_terminated(BitField::parse)?;
+        if fields.is_empty() {
+            return Err(syn::Error::new(
+                brace.span,
+                "expected at least one bit field",
+            ));
+        }
+        Ok(Self {
+            bits,
+            order,
+            brace,
+            fields,
+        })
+    }
+}
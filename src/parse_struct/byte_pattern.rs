@@ -1,18 +1,33 @@
 use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use std::fmt;
+use syn::ext::IdentExt;
 use syn::parse::{Parse, ParseStream};
 
-use crate::hex_string::{HexString, HexValue};
+use crate::hex_string::{HexString, HexStringExt, HexValue};
+
+use super::hex_struct::HexStructBody;
 
 use syn::{
-    bracketed,
+    bracketed, parenthesized,
     punctuated::Punctuated,
     spanned::Spanned,
-    token::{Bracket, Comma},
-    Attribute, Expr, LitByteStr, LitStr, Result,
+    token::{Brace, Bracket, Comma, Dot2},
+    Attribute, Expr, ExprRange, Ident, LitByteStr, LitInt, LitStr, Path, Result, Token,
 };
 
+mod kw {
+    syn::custom_keyword!(skip);
+    syn::custom_keyword!(skip_to);
+    syn::custom_keyword!(align);
+    syn::custom_keyword!(max);
+    syn::custom_keyword!(vec);
+    syn::custom_keyword!(until);
+    syn::custom_keyword!(cstr);
+    syn::custom_keyword!(ci);
+    syn::custom_keyword!(rest);
+}
+
 #[derive(Debug)]
 pub enum BytePattern {
     Array {
@@ -22,13 +37,200 @@ pub enum BytePattern {
     },
     HexString(HexString),
     LitByteStr(LitByteStr),
+    /// A byte string matching letters of either ASCII case: `ci b"GET"`.
+    CaseInsensitive {
+        ci: kw::ci,
+        bstr: LitByteStr,
+    },
+    Skip {
+        skip: kw::skip,
+        count: LitInt,
+    },
+    /// All bytes up to and including the first occurrence of a magic byte sequence:
+    /// `skip_to(MAGIC)`.
+    SkipTo {
+        skip_to: kw::skip_to,
+        magic: Box<Expr>,
+    },
+    Align {
+        align: kw::align,
+        to: LitInt,
+    },
+    Vec {
+        vec: kw::vec,
+        len: Box<Expr>,
+        max: Option<LitInt>,
+    },
+    /// Bytes up to a delimiter, which is read but not included: `until(BYTE)`, or `cstr` for
+    /// null-terminated strings.
+    Until {
+        keyword: Ident,
+        delimiter: Box<Expr>,
+    },
+    /// All remaining bytes up to the end of the input, which are discarded.
+    Rest(Dot2),
+    /// All remaining bytes up to the end of the input, which are kept: `rest`.
+    Remaining(kw::rest),
+    /// No bytes, for fields only given by an expression: `virtual`.
+    Virtual(Token![virtual]),
+    /// A nested struct parsed from the same reader: `PATH { FIELDS }`.
+    Struct(Box<HexStructBody>),
+    /// A constant byte array, the length of which is only known after expansion.
+    Const(Path),
+    /// Nested structs repeated a number of times known at runtime: `[PATH; COUNT] @ { FIELDS }`.
+    Records {
+        count: Box<Expr>,
+        max: Option<LitInt>,
+        body: Box<HexStructBody>,
+    },
 }
 impl BytePattern {
     pub fn len(&self) -> usize {
         match self {
             Self::Array { elems, .. } => elems.len(),
             Self::HexString(hex) => hex.len(),
-            Self::LitByteStr(bstr) => bstr.value().len(),
+            Self::LitByteStr(bstr) | Self::CaseInsensitive { bstr, .. } => bstr.value().len(),
+            Self::Skip { count, .. } => count.base10_parse().unwrap(),
+            Self::Align { .. }
+            | Self::SkipTo { .. }
+            | Self::Rest(_)
+            | Self::Remaining(_)
+            | Self::Virtual(_)
+            | Self::Vec { .. }
+            | Self::Until { .. }
+            | Self::Struct(_)
+            | Self::Const(_)
+            | Self::Records { .. } => 0,
+        }
+    }
+    /// Fails if the pattern doesn't describe constant bytes which can be written.
+    pub fn ensure_constant(&self) -> Result<()> {
+        match self {
+            Self::Array { elems, .. } => {
+                match elems
+                    .iter()
+                    .find(|elem| matches!(elem, Expr::Range(_)) || quote!(#elem).to_string() == "_")
+                {
+                    Some(elem) => Err(syn::Error::new(
+                        elem.span(),
+                        "wildcards can't be written.\n\
+                         help: try using exact bytes or `skip(N)` to write null bytes.",
+                    )),
+                    None => Ok(()),
+                }
+            }
+            Self::HexString(hex) => hex.ensure_bytes("written bytes"),
+            // written as given
+            Self::LitByteStr(_)
+            | Self::CaseInsensitive { .. }
+            | Self::Skip { .. }
+            | Self::SkipTo { .. }
+            | Self::Virtual(_)
+            | Self::Const(_) => Ok(()),
+            Self::Align { align, .. } => {
+                Err(syn::Error::new(align.span, "`align(N)` can't be written"))
+            }
+            Self::Rest(dot2) => Err(syn::Error::new(dot2.span(), "`..` can't be written")),
+            Self::Vec { vec, .. } => Err(syn::Error::new(
+                vec.span,
+                "bytes of unknown length can only be written from struct members",
+            )),
+            Self::Until { keyword, .. } => Err(syn::Error::new(
+                keyword.span(),
+                "bytes of unknown length can only be written from struct members",
+            )),
+            Self::Remaining(rest) => Err(syn::Error::new(
+                rest.span,
+                "bytes of unknown length can only be written from struct members",
+            )),
+            Self::Struct(body) | Self::Records { body, .. } => Err(syn::Error::new(
+                body.path().span(),
+                "nested structs can only be written from struct members",
+            )),
+        }
+    }
+    /// Returns the expression giving the number of bytes to read if it's only known at runtime.
+    pub fn dynamic_len(&self) -> Option<&Expr> {
+        match self {
+            Self::Vec { len, .. } => Some(len),
+            _ => None,
+        }
+    }
+    /// Returns the byte ending the bytes read by `until(BYTE)` or `cstr`.
+    pub fn delimiter(&self) -> Option<&Expr> {
+        match self {
+            Self::Until { delimiter, .. } => Some(delimiter),
+            _ => None,
+        }
+    }
+    /// Returns `true` if the number of bytes read is only known at runtime.
+    pub fn is_dynamic(&self) -> bool {
+        matches!(
+            self,
+            Self::Vec { .. } | Self::Until { .. } | Self::Remaining(_)
+        )
+    }
+    /// Returns the fields of a nested struct or of repeated records.
+    pub fn nested(&self) -> Option<&HexStructBody> {
+        match self {
+            Self::Struct(body) | Self::Records { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+    pub fn nested_mut(&mut self) -> Option<&mut HexStructBody> {
+        match self {
+            Self::Struct(body) | Self::Records { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+    /// Returns the expression giving the number of repeated records.
+    pub fn record_count(&self) -> Option<&Expr> {
+        match self {
+            Self::Records { count, .. } => Some(count),
+            _ => None,
+        }
+    }
+    /// Returns the path of a constant byte array.
+    pub fn constant(&self) -> Option<&Path> {
+        match self {
+            Self::Const(path) => Some(path),
+            _ => None,
+        }
+    }
+    /// Returns the maximum given by `max = N` for lengths known at runtime.
+    pub fn max(&self) -> Option<&LitInt> {
+        match self {
+            Self::Vec { max, .. } | Self::Records { max, .. } => max.as_ref(),
+            _ => None,
+        }
+    }
+    /// Returns `true` if the pattern matches any bytes.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::Skip { .. } | Self::SkipTo { .. } | Self::Align { .. } | Self::Rest(_)
+        )
+    }
+    /// Returns the magic bytes searched for by `skip_to(MAGIC)`.
+    pub fn magic(&self) -> Option<&Expr> {
+        match self {
+            Self::SkipTo { magic, .. } => Some(magic),
+            _ => None,
+        }
+    }
+    /// Returns `true` for `virtual`, which reads no bytes.
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Self::Virtual(_))
+    }
+    /// Returns `true` for `..` and `rest`, which read up to the end of the input.
+    pub fn is_rest(&self) -> bool {
+        matches!(self, Self::Rest(_) | Self::Remaining(_))
+    }
+    /// Returns the alignment of `align(N)` patterns.
+    pub fn alignment(&self) -> Option<usize> {
+        match self {
+            Self::Align { to, .. } => Some(to.base10_parse().unwrap()),
+            _ => None,
         }
     }
 }
@@ -41,6 +243,50 @@ impl fmt::Display for BytePattern {
             }
             Self::HexString(hex) => write!(f, "{}", hex),
             Self::LitByteStr(bstr) => write!(f, "{}", quote!(#bstr)),
+            Self::CaseInsensitive { bstr, .. } => write!(f, "ci {}", quote!(#bstr)),
+            Self::Skip { count, .. } => write!(f, "skip({})", count),
+            Self::SkipTo { magic, .. } => write!(f, "skip_to({})", quote!(#magic)),
+            Self::Align { to, .. } => write!(f, "align({})", to),
+            Self::Rest(_) => write!(f, ".."),
+            Self::Remaining(_) => write!(f, "rest"),
+            Self::Virtual(_) => write!(f, "virtual"),
+            Self::Const(path) => write!(f, "{}", quote!(#path)),
+            Self::Vec { len, max: None, .. } => write!(f, "vec({})", quote!(#len)),
+            Self::Vec {
+                len,
+                max: Some(max),
+                ..
+            } => write!(f, "vec({}, max = {})", quote!(#len), max),
+            Self::Until { keyword, .. } if keyword == "cstr" => write!(f, "cstr"),
+            Self::Until { delimiter, .. } => write!(f, "until({})", quote!(#delimiter)),
+            Self::Struct(body) => {
+                let path = body.path();
+                write!(f, "{} {{ .. }}", quote!(#path))
+            }
+            Self::Records {
+                count,
+                body,
+                max: None,
+                ..
+            } => {
+                let path = body.path();
+                write!(f, "[{}; {}] @ {{ .. }}", quote!(#path), quote!(#count))
+            }
+            Self::Records {
+                count,
+                body,
+                max: Some(max),
+                ..
+            } => {
+                let path = body.path();
+                write!(
+                    f,
+                    "[{}; {}, max = {}] @ {{ .. }}",
+                    quote!(#path),
+                    quote!(#count),
+                    max
+                )
+            }
         }
     }
 }
@@ -49,6 +295,11 @@ impl Parse for BytePattern {
     fn parse(input: ParseStream) -> Result<Self> {
         if input.peek(LitByteStr) {
             Ok(Self::LitByteStr(input.parse::<LitByteStr>()?))
+        } else if input.peek(kw::ci) && input.peek2(LitByteStr) {
+            Ok(Self::CaseInsensitive {
+                ci: input.parse()?,
+                bstr: input.parse()?,
+            })
         } else if input.peek(LitStr) {
             let hex = input.parse::<HexString>()?;
             for elem in hex.elems() {
@@ -61,6 +312,79 @@ impl Parse for BytePattern {
                 }
             }
             Ok(Self::HexString(hex))
+        } else if input.peek(kw::skip) {
+            let content;
+            let skip = input.parse()?;
+            parenthesized!(content in input);
+            let count: LitInt = content.parse()?;
+            count.base10_parse::<usize>()?;
+            Ok(Self::Skip { skip, count })
+        } else if input.peek(kw::skip_to) {
+            let content;
+            let skip_to = input.parse()?;
+            parenthesized!(content in input);
+            Ok(Self::SkipTo {
+                skip_to,
+                magic: content.parse()?,
+            })
+        } else if input.peek(Token![..]) {
+            Ok(Self::Rest(input.parse()?))
+        } else if input.peek(kw::rest) && !input.peek2(Token![::]) && !input.peek2(Brace) {
+            Ok(Self::Remaining(input.parse()?))
+        } else if input.peek(Token![virtual]) {
+            Ok(Self::Virtual(input.parse()?))
+        } else if input.peek(kw::align) {
+            let content;
+            let align = input.parse()?;
+            parenthesized!(content in input);
+            let to: LitInt = content.parse()?;
+            if to.base10_parse::<usize>()? == 0 {
+                return Err(syn::Error::new(
+                    to.span(),
+                    "alignment must be greater than 0",
+                ));
+            }
+            Ok(Self::Align { align, to })
+        } else if input.peek(kw::vec) {
+            let content;
+            let vec = input.parse()?;
+            parenthesized!(content in input);
+            Ok(Self::Vec {
+                vec,
+                len: content.parse()?,
+                max: parse_max(&content)?,
+            })
+        } else if input.peek(kw::until) {
+            let content;
+            let until: kw::until = input.parse()?;
+            parenthesized!(content in input);
+            Ok(Self::Until {
+                keyword: Ident::new("until", until.span),
+                delimiter: content.parse()?,
+            })
+        } else if input.peek(kw::cstr) {
+            let cstr: kw::cstr = input.parse()?;
+            Ok(Self::Until {
+                keyword: Ident::new("cstr", cstr.span),
+                delimiter: Box::new(syn::parse_quote!(0x00)),
+            })
+        } else if is_nested_struct(input) {
+            Ok(Self::Struct(Box::new(input.parse()?)))
+        } else if is_records(input) {
+            let content;
+            bracketed!(content in input);
+            let path = content.parse()?;
+            content.parse::<Token![;]>()?;
+            let count = Box::new(content.parse()?);
+            let max = parse_max(&content)?;
+            input.parse::<Token![@]>()?;
+            Ok(Self::Records {
+                count,
+                max,
+                body: Box::new(HexStructBody::parse_fields(input, vec![], path)?),
+            })
+        } else if input.peek(Ident::peek_any) || input.peek(Token![::]) {
+            Ok(Self::Const(input.parse()?))
         } else {
             let attrs = Attribute::parse_inner(input)?;
 
@@ -71,17 +395,33 @@ impl Parse for BytePattern {
                 Ok((content, bracket))
             })()
             .map_err(|_| {
-                input.error("expected a byte array pattern, a byte string, or a hex string")
+                input.error(
+                    "expected a byte array pattern, a byte string, a hex string, or a constant",
+                )
             })?;
 
             let elems = Punctuated::parse_terminated(&content)?;
+            // ranges of byte values match a single byte each, but `..` and open ranges don't
             for elem in &elems {
-                if let Expr::Range(_) = elem {
-                    return Err(syn::Error::new(
-                        elem.span(),
-                        "ranges are not allowed in byte patterns.\n\
-                        help: try using `_` to specify the exact number of bytes to match.",
-                    ));
+                match elem {
+                    Expr::Range(ExprRange {
+                        from: None,
+                        to: None,
+                        ..
+                    }) => {
+                        return Err(syn::Error::new(
+                            elem.span(),
+                            "`..` is not allowed in byte patterns.\n\
+                            help: try using `_` to specify the exact number of bytes to match.",
+                        ))
+                    }
+                    Expr::Range(ExprRange { to: None, .. }) => {
+                        return Err(syn::Error::new(
+                            elem.span(),
+                            "ranges of byte values must have an end, like `0x80..=0xFF`",
+                        ))
+                    }
+                    _ => {}
                 }
             }
 
@@ -112,6 +452,64 @@ impl ToTokens for BytePattern {
                 let values = bstr.value();
                 quote!([#(#values),*]).to_tokens(tokens);
             }
+            // letters match either case, like nibble wildcards match all their values
+            Self::CaseInsensitive { ci, bstr } => {
+                let values =
+                    bstr.value()
+                        .into_iter()
+                        .map(|value| match value.is_ascii_alphabetic() {
+                            true => {
+                                let (lower, upper) =
+                                    (value.to_ascii_lowercase(), value.to_ascii_uppercase());
+                                quote_spanned!(bstr.span()=>#lower | #upper)
+                            }
+                            false => quote_spanned!(bstr.span()=>#value),
+                        });
+                quote_spanned!(ci.span=>[#(#values),*]).to_tokens(tokens);
+            }
+            Self::Skip { skip, .. } => quote_spanned!(skip.span=>_).to_tokens(tokens),
+            Self::SkipTo { skip_to, .. } => quote_spanned!(skip_to.span=>_).to_tokens(tokens),
+            Self::Align { align, .. } => quote_spanned!(align.span=>_).to_tokens(tokens),
+            Self::Rest(dot2) => quote_spanned!(dot2.span()=>_).to_tokens(tokens),
+            Self::Remaining(rest) => quote_spanned!(rest.span=>_).to_tokens(tokens),
+            Self::Virtual(token) => quote_spanned!(token.span=>_).to_tokens(tokens),
+            Self::Vec { vec, .. } => quote_spanned!(vec.span=>_).to_tokens(tokens),
+            Self::Until { keyword, .. } => quote_spanned!(keyword.span()=>_).to_tokens(tokens),
+            Self::Const(path) => path.to_tokens(tokens),
+            Self::Struct(body) | Self::Records { body, .. } => {
+                quote_spanned!(body.path().span()=>_).to_tokens(tokens)
+            }
         }
     }
 }
+
+/// Parses an optional `, max = N` limit of a length known at runtime.
+fn parse_max(input: ParseStream) -> Result<Option<LitInt>> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    input.parse::<Token![,]>()?;
+    input.parse::<kw::max>()?;
+    input.parse::<Token![=]>()?;
+    let max: LitInt = input.parse()?;
+    max.base10_parse::<usize>()?;
+    Ok(Some(max))
+}
+
+/// Returns `true` if the input starts with `[PATH; COUNT]`.
+fn is_records(input: ParseStream) -> bool {
+    let fork = input.fork();
+    (|| {
+        let content;
+        bracketed!(content in fork);
+        content.parse::<Path>()?;
+        content.parse::<Token![;]>()
+    })()
+    .is_ok()
+}
+
+/// Returns `true` if the input starts with `PATH {`.
+fn is_nested_struct(input: ParseStream) -> bool {
+    let fork = input.fork();
+    Attribute::parse_outer(&fork).is_ok() && fork.parse::<Path>().is_ok() && fork.peek(Brace)
+}
@@ -0,0 +1,412 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+
+use syn::{
+    Attribute, Expr, ExprLit, GenericArgument, Ident, Lit, PathArguments, Result, Token, Type,
+};
+
+use super::internal_ident;
+
+mod kw {
+    syn::custom_keyword!(le);
+    syn::custom_keyword!(be);
+    syn::custom_keyword!(nul);
+}
+
+/// Byte order used for converting bytes to numbers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    Little,
+    Big,
+    /// Given at runtime by a preceding `#[bom]` field.
+    Detected,
+}
+
+impl Endian {
+    /// Parses an optional `le` or `be` keyword.
+    pub fn parse_keyword(input: ParseStream) -> Result<Option<Self>> {
+        if input.peek(kw::le) {
+            input.parse::<kw::le>()?;
+            Ok(Some(Self::Little))
+        } else if input.peek(kw::be) {
+            input.parse::<kw::be>()?;
+            Ok(Some(Self::Big))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Removes `#[le]` and `#[be]` attributes, returning the byte order they specify.
+    pub fn take_attribute(attrs: &mut Vec<Attribute>) -> Result<Option<Self>> {
+        let mut endian = None;
+        let mut result = Ok(());
+        attrs.retain(|attr| {
+            let value = if attr.path.is_ident("le") {
+                Self::Little
+            } else if attr.path.is_ident("be") {
+                Self::Big
+            } else {
+                return true;
+            };
+            if endian.replace(value).is_some() {
+                result = Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate byte order attribute",
+                ));
+            }
+            false
+        });
+        result.map(|_| endian)
+    }
+}
+
+/// Parses the `le` or `be` argument of `#[endian(...)]`.
+impl Parse for Endian {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Self::parse_keyword(input)?.ok_or_else(|| input.error("expected `le` or `be`"))
+    }
+}
+
+/// Kinds of types which bytes can be converted to.
+#[derive(Debug)]
+enum Kind {
+    /// Primitive numbers converted with `from_le_bytes` or `from_be_bytes`.
+    Primitive { size: usize },
+    /// Arrays of primitive numbers, each converted from `size` consecutive bytes.
+    Array { size: usize, count: usize },
+    /// UTF-8 strings, optionally ending at the first null byte.
+    String { nul: Option<kw::nul> },
+}
+
+/// Type which the bytes of a field are converted to: `FIELD: TYPE @ [le|be|nul] PATTERN`.
+#[derive(Debug)]
+pub struct FieldType {
+    ty: Type,
+    /// Name of the type, or of the element type of arrays.
+    name: Ident,
+    kind: Kind,
+    endian: Option<Endian>,
+    /// Missing bytes at the end of the input result in `None`.
+    optional: bool,
+}
+
+impl FieldType {
+    /// Returns `Some(optional)` if bytes can be converted to the type, where `optional` is
+    /// `true` for `Option<TYPE>`.
+    pub fn convertible(ty: &Type) -> Option<bool> {
+        let (ty, optional) = match option_inner(ty) {
+            Some(inner) => (inner, true),
+            None => (ty, false),
+        };
+        match type_kind(ty) {
+            Ok(Some(_)) => Some(optional),
+            _ => None,
+        }
+    }
+
+    /// Parses `TYPE @ [le|be|nul]` or `Option<TYPE> @? [le|be|nul]` if the input starts with
+    /// a supported type.
+    pub fn parse_typed(input: ParseStream) -> Result<Option<Self>> {
+        let fork = input.fork();
+        let ty = match fork.parse::<Type>() {
+            Ok(ty) if fork.peek(Token![@]) => ty,
+            _ => return Ok(None),
+        };
+        let (ty, option) = match option_inner(&ty) {
+            Some(inner) => (inner.clone(), Some(ty)),
+            None => (ty, None),
+        };
+        let (name, kind) = match type_kind(&ty)? {
+            Some(name_kind) => name_kind,
+            None => return Ok(None),
+        };
+
+        input.parse::<Type>()?;
+        input.parse::<Token![@]>()?;
+        let optional = input.parse::<Option<Token![?]>>()?;
+        let mut field_type = Self {
+            ty,
+            name,
+            kind,
+            endian: None,
+            optional: optional.is_some(),
+        };
+        match (&option, &optional) {
+            (Some(option), None) => {
+                return Err(syn::Error::new_spanned(
+                    option,
+                    "expected `@?` for an optional field",
+                ))
+            }
+            (None, Some(question)) => {
+                return Err(syn::Error::new(
+                    question.span,
+                    format!(
+                        "optional fields must have the type `Option<{}>`",
+                        field_type.type_string()
+                    ),
+                ))
+            }
+            _ => (),
+        }
+        field_type.endian = Endian::parse_keyword(input)?;
+        if input.peek(kw::nul) {
+            let nul = input.parse::<kw::nul>()?;
+            match &mut field_type.kind {
+                Kind::String { nul: string_nul } => *string_nul = Some(nul),
+                _ => {
+                    return Err(syn::Error::new(
+                        nul.span,
+                        "`nul` can only be used with `String` fields",
+                    ))
+                }
+            }
+        }
+        Ok(Some(field_type))
+    }
+
+    /// Returns the number of bytes the type is converted from, if it's fixed.
+    pub fn size(&self) -> Option<usize> {
+        match self.kind {
+            Kind::Primitive { size } => Some(size),
+            Kind::Array { size, count } => Some(size * count),
+            Kind::String { .. } => None,
+        }
+    }
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+    pub fn is_string(&self) -> bool {
+        matches!(self.kind, Kind::String { .. })
+    }
+    pub fn name(&self) -> &Ident {
+        &self.name
+    }
+    /// Returns the type as written in messages.
+    pub fn type_string(&self) -> String {
+        match self.kind {
+            Kind::Array { count, .. } => format!("[{}; {}]", self.name, count),
+            _ => self.name.to_string(),
+        }
+    }
+    pub fn set_endian(&mut self, endian: Option<Endian>) -> Result<()> {
+        match (self.endian, endian) {
+            (Some(_), Some(_)) => Err(syn::Error::new(
+                self.name.span(),
+                "byte order is specified more than once",
+            )),
+            (None, endian) => {
+                self.endian = endian;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Uses the given byte order if none is specified and it matters for the conversion, such
+    /// as the one detected by a preceding `#[bom]` field or the default of the struct.
+    pub fn default_endian(&mut self, endian: Endian) {
+        match (&self.kind, self.endian) {
+            (Kind::Primitive { size } | Kind::Array { size, .. }, None) if *size > 1 => {
+                self.endian = Some(endian)
+            }
+            _ => (),
+        }
+    }
+
+    /// Checks that the byte order is known if it matters for the conversion.
+    pub fn check_endian(&self) -> Result<()> {
+        match (&self.kind, self.endian) {
+            (Kind::Primitive { size } | Kind::Array { size, .. }, None) if *size > 1 => {
+                Err(syn::Error::new(
+                    self.name.span(),
+                    format!(
+                        "expected the byte order of `{}`\n\
+                     help: add `le` or `be` after `@`",
+                        self.name
+                    ),
+                ))
+            }
+            (Kind::String { .. }, Some(_)) => Err(syn::Error::new(
+                self.name.span(),
+                "byte order can't be specified for `String` fields",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an expression converting the bytes in `buffer` to the type, with errors
+    /// created from the `io` module.
+    pub fn conversion(&self, buffer: &Ident, io: &TokenStream) -> TokenStream {
+        let ty = &self.ty;
+        match (&self.kind, self.endian) {
+            (Kind::Primitive { .. }, Some(Endian::Little) | None) => {
+                quote!(#ty::from_le_bytes(*#buffer))
+            }
+            (Kind::Primitive { .. }, Some(Endian::Big)) => quote!(#ty::from_be_bytes(*#buffer)),
+            (Kind::Primitive { .. }, Some(Endian::Detected)) => {
+                let big_endian_ident = internal_ident("BIG_ENDIAN", self.name.span());
+                quote!(if #big_endian_ident {
+                    #ty::from_be_bytes(*#buffer)
+                } else {
+                    #ty::from_le_bytes(*#buffer)
+                })
+            }
+            (Kind::Array { size, count }, endian) => {
+                // indices are known to be in bounds, so the conversion can't panic
+                let name = &self.name;
+                let elems = |from_bytes: TokenStream| {
+                    let elems = (0..*count).map(|i| {
+                        let bytes = (i * size..(i + 1) * size).map(|j| quote!(#buffer[#j]));
+                        quote!(#name::#from_bytes([#(#bytes),*]))
+                    });
+                    quote!([#(#elems),*])
+                };
+                match endian {
+                    Some(Endian::Big) => elems(quote!(from_be_bytes)),
+                    Some(Endian::Detected) => {
+                        let big_endian_ident = internal_ident("BIG_ENDIAN", self.name.span());
+                        let be = elems(quote!(from_be_bytes));
+                        let le = elems(quote!(from_le_bytes));
+                        quote!(if #big_endian_ident { #be } else { #le })
+                    }
+                    _ => elems(quote!(from_le_bytes)),
+                }
+            }
+            (Kind::String { nul }, _) => {
+                let bytes = match nul {
+                    Some(_) => quote!(#buffer
+                        .split(|byte| *byte == 0)
+                        .next()
+                        .unwrap_or_default()),
+                    None => quote!(&#buffer[..]),
+                };
+                quote!(
+                    #ty::from_utf8((#bytes).to_vec())
+                        .map_err(|err| #io::Error::new(#io::ErrorKind::InvalidData, err))?
+                )
+            }
+        }
+    }
+
+    /// Returns an expression converting `value` of the type to bytes, the inverse of
+    /// [`conversion`](FieldType::conversion). `len` is the number of bytes the value must fill.
+    pub fn to_bytes(&self, value: &Ident, len: Option<usize>) -> TokenStream {
+        // `write_struct!` has no `#[bom]` fields, so the byte order is never detected
+        match (&self.kind, self.endian, len) {
+            (Kind::Primitive { .. }, Some(Endian::Little | Endian::Detected) | None, _) => {
+                quote!(#value.to_le_bytes())
+            }
+            (Kind::Primitive { .. }, Some(Endian::Big), _) => quote!(#value.to_be_bytes()),
+            (Kind::Array { size, count }, endian, _) => {
+                let to_bytes = match endian {
+                    Some(Endian::Big) => quote!(to_be_bytes),
+                    _ => quote!(to_le_bytes),
+                };
+                let len = size * count;
+                quote!({
+                    let mut bytes = [0u8; #len];
+                    for (chunk, value) in bytes.chunks_exact_mut(#size).zip(#value.iter()) {
+                        chunk.copy_from_slice(&value.#to_bytes());
+                    }
+                    bytes
+                })
+            }
+            (Kind::String { .. }, _, None) => quote!(#value.as_bytes()),
+            (Kind::String { nul }, _, Some(len)) => {
+                let value_string = value.to_string();
+                let too_long = match nul {
+                    Some(_) => quote!(bytes.len() > #len),
+                    None => quote!(bytes.len() != #len),
+                };
+                quote!({
+                    let bytes = #value.as_bytes();
+                    if #too_long {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            format!("expected {} bytes for `{}`, got {}", #len, #value_string, bytes.len()),
+                        ));
+                    }
+                    let mut padded = [0u8; #len];
+                    padded[..bytes.len()].copy_from_slice(bytes);
+                    padded
+                })
+            }
+        }
+    }
+}
+
+/// Returns the name and kind of a supported type.
+fn type_kind(ty: &Type) -> Result<Option<(Ident, Kind)>> {
+    let (name, count) = match ty {
+        Type::Array(array) => match (type_ident(&array.elem), &array.len) {
+            (
+                Some(ident),
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(len), ..
+                }),
+            ) => (ident, Some(len.base10_parse::<usize>()?)),
+            _ => return Ok(None),
+        },
+        ty => match type_ident(ty) {
+            Some(ident) => (ident, None),
+            None => return Ok(None),
+        },
+    };
+    let kind = match (name.to_string().as_str(), primitive_size(&name), count) {
+        (_, Some(size), None) => Kind::Primitive { size },
+        (_, Some(size), Some(count)) => Kind::Array { size, count },
+        ("String", _, None) => Kind::String { nul: None },
+        _ => return Ok(None),
+    };
+    Ok(Some((name, kind)))
+}
+
+/// Returns the name of a type given by a single identifier.
+fn type_ident(ty: &Type) -> Option<Ident> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().cloned(),
+        _ => None,
+    }
+}
+
+/// Returns `T` if the type is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() && path.path.segments.len() == 1 => {
+            &path.path.segments[0]
+        }
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args)
+            if segment.ident == "Option" && args.args.len() == 1 =>
+        {
+            match &args.args[0] {
+                GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the size of supported primitive number types.
+pub(crate) fn primitive_size(ident: &Ident) -> Option<usize> {
+    match ident.to_string().as_str() {
+        "u8" => Some(1),
+        "u16" => Some(2),
+        "u32" => Some(4),
+        "u64" => Some(8),
+        "u128" => Some(16),
+        "i8" => Some(1),
+        "i16" => Some(2),
+        "i32" => Some(4),
+        "i64" => Some(8),
+        "i128" => Some(16),
+        "f32" => Some(4),
+        "f64" => Some(8),
+        _ => None,
+    }
+}
@@ -0,0 +1,330 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use syn::{
+    braced,
+    ext::IdentExt,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::{Brace, Comma, FatArrow},
+    Attribute, Expr, Ident, LitInt, Path, Result, Token,
+};
+
+use super::{
+    byte_pattern::BytePattern,
+    call_once,
+    hex_struct::{peek_modifier, take_attribute, HexStructBody},
+    internal_ident, turbofish, HexStructField, ReadOptions,
+};
+
+/// Pattern matching the tag which selects a variant.
+#[derive(Debug)]
+enum TagPattern {
+    /// A single byte given as an integer literal: `0x01`.
+    Byte(LitInt),
+    Bytes(BytePattern),
+}
+
+impl TagPattern {
+    fn len(&self) -> usize {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Bytes(pattern) => pattern.len(),
+        }
+    }
+    fn span(&self) -> Span {
+        match self {
+            Self::Byte(byte) => byte.span(),
+            Self::Bytes(pattern) => pattern.span(),
+        }
+    }
+}
+
+impl Parse for TagPattern {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(LitInt) {
+            let byte: LitInt = input.parse()?;
+            byte.base10_parse::<u8>()?;
+            return Ok(Self::Byte(byte));
+        }
+        let pattern: BytePattern = input.parse()?;
+        // other patterns read no bytes or a number of bytes the tag can't be matched against
+        let fixed = match &pattern {
+            BytePattern::HexString(hex) => hex.dot2().is_none(),
+            BytePattern::Array { .. }
+            | BytePattern::LitByteStr(_)
+            | BytePattern::CaseInsensitive { .. } => true,
+            _ => false,
+        };
+        if !fixed || pattern.len() == 0 {
+            return Err(syn::Error::new(
+                pattern.span(),
+                "expected a tag of a fixed number of bytes.\n\
+                 help: tags are integer literals, hex strings, byte strings or byte arrays",
+            ));
+        }
+        Ok(Self::Bytes(pattern))
+    }
+}
+
+impl ToTokens for TagPattern {
+    fn to_tokens(&self, stream: &mut TokenStream) {
+        match self {
+            Self::Byte(byte) => quote!([#byte]).to_tokens(stream),
+            Self::Bytes(pattern) => pattern.to_tokens(stream),
+        }
+    }
+}
+
+/// A variant of the enum and the tag selecting it: `TAG => VARIANT { fields }`.
+#[derive(Debug)]
+struct HexVariant {
+    tag: TagPattern,
+    fat_arrow: FatArrow,
+    body: HexStructBody,
+}
+
+impl Parse for HexVariant {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let variant = Self {
+            tag: input.parse()?,
+            fat_arrow: input.parse()?,
+            body: input.parse()?,
+        };
+        if variant.body.is_tuple() {
+            return Err(syn::Error::new(
+                variant.body.path().span(),
+                "expected the path of an enum variant",
+            ));
+        }
+        Ok(variant)
+    }
+}
+
+#[derive(Debug)]
+pub struct HexEnum {
+    /// Returns a future, awaiting each read.
+    asyncness: Option<Token![async]>,
+    reader: Expr,
+    /// Module replacing `std::io` given by `#[io(PATH)]`.
+    io: Option<Path>,
+    /// Binding of the tag's bytes in the variants: `BINDING @ ENUM`.
+    tag_binding: Option<Ident>,
+    path: Path,
+    brace: Brace,
+    /// Fields read after the tag and before the variant, which are in scope in all variants.
+    header: Vec<HexStructField>,
+    variants: Punctuated<HexVariant, Comma>,
+    tag_len: usize,
+}
+
+/// Returns `true` if the input continues with a field rather than a variant.
+fn peek_field(input: ParseStream) -> bool {
+    input.peek(Token![#])
+        || ((input.peek(Ident::peek_any) || input.peek(Token![_]))
+            && input.peek2(Token![:])
+            && !input.peek2(Token![::]))
+}
+
+/// Fails at the `seek(N)` or `@reader(READER)` of the fields, since enums are read from a
+/// single reader without seeking.
+fn ensure_sequential(seek: Option<Span>, reader: Option<Span>) -> Result<()> {
+    match (seek, reader) {
+        (Some(span), _) => Err(syn::Error::new(span, "fields of enums can't seek")),
+        (None, Some(span)) => Err(syn::Error::new(
+            span,
+            "fields of enums can't switch readers",
+        )),
+        (None, None) => Ok(()),
+    }
+}
+
+impl Parse for HexEnum {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let asyncness = match peek_modifier(input, Token![async]) {
+            true => Some(input.parse()?),
+            false => None,
+        };
+        let reader = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let mut attrs = Attribute::parse_outer(input)?;
+        let io = take_attribute(&mut attrs, "io")?;
+        if let Some(attr) = attrs.first() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[io(PATH)]` before the enum",
+            ));
+        }
+        let tag_binding = match input.peek(Ident) && input.peek2(Token![@]) {
+            true => {
+                let ident = input.parse()?;
+                input.parse::<Token![@]>()?;
+                Some(ident)
+            }
+            false => None,
+        };
+        let mut path = input.parse()?;
+        turbofish(&mut path);
+        let content;
+        let brace = braced!(content in input);
+
+        let mut header = vec![];
+        let mut bom = false;
+        while peek_field(&content) {
+            let mut field: HexStructField = content.parse()?;
+            content.parse::<Token![,]>()?;
+            field.resolve_endian(bom, None)?;
+            field.resolve_error_kind(None)?;
+            let nested_reader = field.byte_pattern().nested().and_then(|b| b.reader_span());
+            ensure_sequential(field.seek_span(), field.reader_span().or(nested_reader))?;
+            bom |= field.is_bom();
+            header.push(field);
+        }
+
+        let mut variants: Punctuated<HexVariant, Comma> = Punctuated::parse_terminated(&content)?;
+        for variant in variants.iter_mut() {
+            variant.body.resolve_endian(bom, None)?;
+            variant.body.resolve_error_kind(None)?;
+            ensure_sequential(variant.body.seek_span(), variant.body.reader_span())?;
+        }
+
+        let tag_len = match variants.first() {
+            Some(variant) => variant.tag.len(),
+            None => return Err(syn::Error::new(brace.span, "expected at least one variant")),
+        };
+        if let Some(variant) = variants.iter().find(|v| v.tag.len() != tag_len) {
+            return Err(syn::Error::new(
+                variant.tag.span(),
+                format!(
+                    "expected a tag of {} bytes, found {}\n\
+                     help: all tags must have the same length",
+                    tag_len,
+                    variant.tag.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            asyncness,
+            reader,
+            io,
+            tag_binding,
+            path,
+            brace,
+            header,
+            variants,
+            tag_len,
+        })
+    }
+}
+
+impl ToTokens for HexEnum {
+    fn to_tokens(&self, output_stream: &mut TokenStream) {
+        let options = ReadOptions {
+            is_async: self.asyncness.is_some(),
+            io: self.io.clone(),
+            ..ReadOptions::default()
+        };
+        let io = options.io();
+
+        let mut closure_stream = TokenStream::new();
+        self.brace.surround(&mut closure_stream, |stream| {
+            let HexEnum {
+                reader,
+                tag_binding,
+                path,
+                header,
+                variants,
+                tag_len,
+                ..
+            } = self;
+
+            // setup
+            let array_ident = internal_ident("ARRAY", reader.span());
+            let len = variants
+                .iter()
+                .map(|variant| variant.body.max_len(&options))
+                .chain(
+                    header
+                        .iter()
+                        .filter(|field| !field.is_boxed())
+                        .map(|field| field.byte_pattern().len()),
+                )
+                .chain(Some(*tag_len))
+                .max()
+                .unwrap_or_default();
+
+            let reader_ident = internal_ident("READER", reader.span());
+            let tag_ident = internal_ident("TAG", reader.span());
+            let count_ident = internal_ident("COUNT", reader.span());
+            let read_tag =
+                options.read_exact(&reader_ident, quote!(&mut #array_ident[0..#tag_len]));
+            let tag = options.try_into_array(quote!(&#array_ident[0..#tag_len]), quote!(#tag_len));
+            quote!(
+                 use ::core::convert::TryInto;
+
+                 #[allow(non_snake_case)]
+                 let mut #reader_ident = #reader;
+
+                 #[allow(non_snake_case)]
+                 let mut #array_ident: [u8; #len] = [0; #len];
+
+                 #[allow(non_snake_case, unused_mut)]
+                 let mut #count_ident: usize = #tag_len;
+
+                 #read_tag?;
+                 #[allow(non_snake_case)]
+                 let #tag_ident: [u8; #tag_len] = #tag;
+            )
+            .to_tokens(stream);
+            if let Some(tag_binding) = tag_binding {
+                quote!(let #tag_binding: [u8; #tag_len] = #tag_ident;).to_tokens(stream);
+            }
+
+            // header fields shared by the variants
+            for field in header {
+                field.to_read_tokens(&options, stream);
+            }
+
+            // variants
+            let mut arms_stream = TokenStream::new();
+            for variant in variants {
+                let HexVariant {
+                    tag,
+                    fat_arrow,
+                    body,
+                } = variant;
+                let mut arm_stream = TokenStream::new();
+                body.to_field_tokens(&options, &mut arm_stream);
+                let instantiation = body.instantiation(Some(path));
+                quote!(
+                    #[allow(dead_code)]
+                    #tag #fat_arrow {
+                        #arm_stream
+                        let _ = #count_ident;
+                        Ok(#instantiation)
+                    }
+                )
+                .to_tokens(&mut arms_stream);
+            }
+            quote!(
+                #[allow(unreachable_patterns)]
+                match #tag_ident {
+                    #arms_stream
+                    _ => Err(#io::Error::new(
+                        #io::ErrorKind::InvalidData,
+                        format!("unexpected tag `{:02X?}`", #tag_ident),
+                    )),
+                }
+            )
+            .to_tokens(stream);
+        });
+
+        match &self.asyncness {
+            Some(_) => quote!(async { #closure_stream }),
+            None => call_once(quote!(|| { #closure_stream })),
+        }
+        .to_tokens(output_stream);
+    }
+}
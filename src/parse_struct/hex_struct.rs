@@ -1,22 +1,93 @@
-use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
-use syn::parse::{Parse, ParseStream};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::parse::{Parse, ParseStream, Parser, Peek};
 
 use syn::{
     braced,
     punctuated::Punctuated,
     spanned::Spanned,
-    token::{Brace, Comma, Dot2},
-    Attribute, Expr, Path, Result, Token,
+    token::{Brace, Bracket, Colon, Comma, Dot2, Paren},
+    Attribute, Expr, Ident, Member, Path, Result, Token, Type, TypeTuple,
 };
 
-use super::{hex_struct_field::HexStructField, internal_ident};
+use super::{
+    byte_pattern::BytePattern,
+    call_once,
+    field_type::Endian,
+    hex_struct_field::{sort_by_order, HexStructField},
+    internal_ident, turbofish, FieldType, ReadOptions,
+};
+
+mod kw {
+    syn::custom_keyword!(counted);
+    syn::custom_keyword!(threaded);
+    syn::custom_keyword!(seekable);
+    syn::custom_keyword!(buffered);
+    syn::custom_keyword!(collect);
+    syn::custom_keyword!(exact);
+    syn::custom_keyword!(trace);
+}
 
 #[derive(Debug)]
 pub struct HexStruct {
+    /// Returns the number of bytes read along with the struct.
+    counted: Option<kw::counted>,
+    /// Returns the reader along with the struct.
+    threaded: Option<kw::threaded>,
+    /// Collects all mismatched fields instead of returning the first one.
+    collect: Option<kw::collect>,
+    /// Fails if any bytes remain after the last field.
+    exact: Option<kw::exact>,
+    /// Prints each field to stderr after it's read, if the `trace` feature is enabled.
+    trace: Option<kw::trace>,
+    /// Returns a future, awaiting each read.
+    asyncness: Option<Token![async]>,
+    /// Seeks over skipped bytes instead of reading them.
+    seekable: Option<kw::seekable>,
+    /// Length of the whole struct, which is read at once when given by `buffered`.
+    frame_len: Option<usize>,
     reader: Expr,
+    /// Buffer fixed-length fields are read into, given by `READER, SCRATCH =>`.
+    scratch: Option<Expr>,
+    /// Error type given by `#[error(TYPE)]`, which `std::io::Error` is converted into.
+    error: Option<Type>,
+    /// Module replacing `std::io` given by `#[io(PATH)]`.
+    io: Option<Path>,
+    /// Type describing mismatched bytes given by `#[mismatch(TYPE)]`.
+    mismatch: Option<Type>,
+    /// Fields borrow from the reader, which is a `&[u8]`.
+    slice: bool,
+    body: HexStructBody,
+}
+
+/// Input of the `parse_slice!` macro: the input of `parse_struct!` with a `&[u8]` reader.
+#[derive(Debug)]
+pub struct HexSlice(HexStruct);
+
+/// Type instantiated from the fields of a struct.
+#[derive(Debug)]
+pub enum Target {
+    /// A struct, or an enum variant: `PATH { fields }`.
+    Path(Path),
+    /// A tuple, the elements of which are given by fields named by their index:
+    /// `(TYPE, ...) { 0: PATTERN, ... }`.
+    Tuple(TypeTuple),
+}
+
+impl ToTokens for Target {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Self::Path(path) => path.to_tokens(tokens),
+            Self::Tuple(tuple) => tuple.to_tokens(tokens),
+        }
+    }
+}
+
+/// Fields of a struct and the path used to instantiate it: `#attrs PATH { fields [, ..rest] }`.
+#[derive(Debug)]
+pub struct HexStructBody {
     attrs: Vec<Attribute>,
-    path: Path,
+    path: Target,
     brace: Brace,
     fields: Punctuated<HexStructField, Comma>,
     dot2_token: Option<Dot2>,
@@ -25,11 +96,222 @@ pub struct HexStruct {
 
 impl Parse for HexStruct {
     fn parse(input: ParseStream) -> Result<Self> {
+        Self::parse_with(input, false)
+    }
+}
+
+impl HexStruct {
+    /// Parses the input of `parse_struct!`, or of `parse_slice!` if `slice` is `true`.
+    fn parse_with(input: ParseStream, slice: bool) -> Result<Self> {
+        let mut counted = None;
+        let mut threaded = None;
+        let mut asyncness = None;
+        let mut seekable: Option<kw::seekable> = None;
+        let mut buffered: Option<kw::buffered> = None;
+        let mut collect = None;
+        let mut exact = None;
+        let mut trace = None;
+        loop {
+            if peek_modifier(input, kw::counted) {
+                counted = Some(input.parse()?);
+            } else if peek_modifier(input, kw::threaded) {
+                threaded = Some(input.parse()?);
+            } else if peek_modifier(input, kw::collect) {
+                collect = Some(input.parse()?);
+            } else if peek_modifier(input, kw::exact) {
+                exact = Some(input.parse()?);
+            } else if peek_modifier(input, kw::trace) {
+                trace = Some(input.parse()?);
+            } else if peek_modifier(input, Token![async]) {
+                asyncness = Some(input.parse()?);
+            } else if peek_modifier(input, kw::seekable) {
+                seekable = Some(input.parse()?);
+            } else if peek_modifier(input, kw::buffered) {
+                buffered = Some(input.parse()?);
+            } else {
+                break;
+            }
+        }
         let reader = input.parse()?;
+        let scratch = match input.parse::<Option<Token![,]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
         input.parse::<Token![=>]>()?;
+        let mut body: HexStructBody = input.parse()?;
+        body.resolve_endian(false, None)?;
+        body.resolve_error_kind(None)?;
+        let error = body.take_attribute("error")?;
+        let io = body.take_attribute("io")?;
+        let mismatch = body.take_attribute("mismatch")?;
+        let frame_len = match buffered {
+            Some(_) => {
+                if let Some(seekable) = &seekable {
+                    return Err(syn::Error::new(
+                        seekable.span,
+                        "buffered structs are already in memory and can't be seeked",
+                    ));
+                }
+                Some(body.frame_len(0)?)
+            }
+            None => None,
+        };
+        // slices are read at an offset which can be moved without seeking
+        if seekable.is_none() && !slice {
+            if let Some(span) = body.seek_span() {
+                return Err(syn::Error::new(
+                    span,
+                    "fields can only seek in `seekable` structs.\n\
+                     help: add `seekable` before the reader, which must implement `Seek`",
+                ));
+            }
+        }
+
+        // nested fields are read in their own scope, which the reader wouldn't outlive
+        let nested_reader = body
+            .fields()
+            .iter()
+            .find_map(|field| field.byte_pattern().nested()?.reader_span());
+        if let Some(span) = nested_reader {
+            return Err(syn::Error::new(
+                span,
+                "readers can only be switched between fields of the outermost struct",
+            ));
+        }
+        if let Some(span) = body.reader_span() {
+            if slice {
+                return Err(syn::Error::new(
+                    span,
+                    "slices are read at an offset and can't switch readers",
+                ));
+            }
+            if frame_len.is_some() {
+                return Err(syn::Error::new(
+                    span,
+                    "buffered structs are read at once and can't switch readers",
+                ));
+            }
+        }
+
+        Ok(HexStruct {
+            counted,
+            threaded,
+            collect,
+            exact,
+            trace,
+            asyncness,
+            seekable,
+            frame_len,
+            reader,
+            scratch,
+            error,
+            io,
+            mismatch,
+            slice,
+            body,
+        })
+    }
+
+    /// Returns `true` if any field is read up to a delimiter, which requires a `BufRead` reader.
+    pub fn is_delimited(&self) -> bool {
+        self.body.is_delimited()
+    }
+}
 
+impl Parse for HexSlice {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let hex_struct = HexStruct::parse_with(input, true)?;
+        if let Some(asyncness) = &hex_struct.asyncness {
+            return Err(syn::Error::new(
+                asyncness.span,
+                "slices can't be parsed asynchronously",
+            ));
+        }
+        if hex_struct.frame_len.is_some() {
+            return Err(input.error("slices are already in memory and can't be buffered"));
+        }
+        if let Some(seekable) = &hex_struct.seekable {
+            return Err(syn::Error::new(
+                seekable.span,
+                "skipped bytes of slices are never read.\n\
+                 help: remove `seekable`",
+            ));
+        }
+        if let Some(scratch) = &hex_struct.scratch {
+            return Err(syn::Error::new(
+                scratch.span(),
+                "fields of slices are borrowed and don't need a scratch buffer",
+            ));
+        }
+        Ok(Self(hex_struct))
+    }
+}
+
+impl ToTokens for HexSlice {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.0.to_tokens(tokens)
+    }
+}
+
+impl Parse for HexStructBody {
+    fn parse(input: ParseStream) -> Result<Self> {
         let attrs = Attribute::parse_outer(input)?;
+        if input.peek(Paren) {
+            return Self::parse_tuple(input, attrs);
+        }
         let path = input.parse()?;
+        Self::parse_fields(input, attrs, path)
+    }
+}
+
+impl HexStructBody {
+    /// Parses the braced fields of a struct with the given path.
+    pub fn parse_fields(input: ParseStream, attrs: Vec<Attribute>, mut path: Path) -> Result<Self> {
+        turbofish(&mut path);
+        Self::parse_fields_with(input, attrs, Target::Path(path), HexStructField::parse)
+    }
+
+    /// Parses the braced fields of a tuple, which are typed by the elements of the tuple unless
+    /// they give a type themselves.
+    fn parse_tuple(input: ParseStream, attrs: Vec<Attribute>) -> Result<Self> {
+        let tuple: TypeTuple = input.parse()?;
+        let elems: Vec<Type> = tuple.elems.iter().cloned().collect();
+        let body = Self::parse_fields_with(input, attrs, Target::Tuple(tuple), |input| {
+            parse_tuple_field(input, &elems)
+        })?;
+        if let Some(dot2) = &body.dot2_token {
+            return Err(syn::Error::new(
+                dot2.span(),
+                "tuples can't be instantiated with `..`",
+            ));
+        }
+        let mut found = vec![false; elems.len()];
+        for field in &body.fields {
+            if let Some(index) = field.tuple_index() {
+                if std::mem::replace(&mut found[index], true) {
+                    return Err(syn::Error::new(
+                        field.byte_pattern().span(),
+                        format!("duplicate field `{}`", index),
+                    ));
+                }
+            }
+        }
+        if let Some(index) = found.iter().position(|found| !found) {
+            return Err(syn::Error::new(
+                body.brace.span,
+                format!("missing field `{}` of the tuple", index),
+            ));
+        }
+        Ok(body)
+    }
+
+    /// Parses the braced fields of a struct, each with `parse_field`.
+    fn parse_fields_with(
+        input: ParseStream,
+        attrs: Vec<Attribute>,
+        path: Target,
+        parse_field: impl Fn(ParseStream) -> Result<HexStructField>,
+    ) -> Result<Self> {
         let content;
         let brace = braced!(content in input);
         let mut fields = Punctuated::new();
@@ -37,11 +319,10 @@ impl Parse for HexStruct {
         while !content.is_empty() {
             if content.peek(Token![..]) {
                 return Ok(Self {
-                    reader,
                     attrs,
                     path,
                     brace,
-                    fields,
+                    fields: sort_by_order(fields)?,
                     dot2_token: Some(content.parse()?),
                     rest: if content.is_empty() {
                         None
@@ -51,94 +332,514 @@ impl Parse for HexStruct {
                 });
             }
 
-            fields.push(content.parse()?);
+            let field = parse_field(&content)?;
+            let rest = field
+                .byte_pattern()
+                .is_rest()
+                .then(|| field.byte_pattern().to_string());
+            fields.push(field);
             if content.is_empty() {
                 break;
             }
             let punct: Token![,] = content.parse()?;
             fields.push_punct(punct);
+            if let Some(rest) = rest.filter(|_| !content.is_empty()) {
+                return Err(content.error(format!("`{}` must be the last field", rest)));
+            }
         }
 
-        Ok(HexStruct {
-            reader,
+        Ok(HexStructBody {
             attrs,
             path,
             brace,
-            fields,
+            fields: sort_by_order(fields)?,
             dot2_token: None,
             rest: None,
         })
     }
+
+    /// Removes the `#[NAME(...)]` attribute, returning its parsed argument.
+    fn take_attribute<T: Parse>(&mut self, name: &str) -> Result<Option<T>> {
+        take_attribute(&mut self.attrs, name)
+    }
+
+    /// Checks that typed fields have a byte order, detecting it at runtime after a `#[bom]`
+    /// field. `bom` is `true` if an enclosing struct already read one, and `endian` is the
+    /// default of the enclosing struct, which is replaced by an `#[endian(le|be)]` attribute.
+    pub fn resolve_endian(&mut self, mut bom: bool, endian: Option<Endian>) -> Result<()> {
+        let endian = self.take_attribute("endian")?.or(endian);
+        for field in self.fields.iter_mut() {
+            field.resolve_endian(bom, endian)?;
+            bom |= field.is_bom();
+        }
+        Ok(())
+    }
+
+    /// Sets the kind of mismatch errors of the fields, replacing the one of the enclosing
+    /// struct (`kind`) with an `#[error_kind(KIND)]` attribute.
+    pub fn resolve_error_kind(&mut self, kind: Option<Ident>) -> Result<()> {
+        let kind = self.take_attribute("error_kind")?.or(kind);
+        self.fields
+            .iter_mut()
+            .try_for_each(|field| field.resolve_error_kind(kind.as_ref()))
+    }
+
+    /// Returns the offset following the fields when they start at `offset`, failing if their
+    /// length isn't known at compile time.
+    fn frame_len(&self, offset: usize) -> Result<usize> {
+        self.fields.iter().try_fold(offset, |offset, field| {
+            let byte_pattern = field.byte_pattern();
+            if let Some(alignment) = byte_pattern.alignment() {
+                return Ok(offset + (alignment - offset % alignment) % alignment);
+            }
+            match byte_pattern {
+                BytePattern::Struct(body) => body.frame_len(offset),
+                BytePattern::Vec { .. }
+                | BytePattern::Until { .. }
+                | BytePattern::SkipTo { .. }
+                | BytePattern::Rest(_)
+                | BytePattern::Remaining(_)
+                | BytePattern::Const(_)
+                | BytePattern::Records { .. } => Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "buffered structs can only contain fields of a fixed length",
+                )),
+                _ if field.is_optional() => Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "buffered structs can't contain optional fields",
+                )),
+                _ => Ok(offset + byte_pattern.len()),
+            }
+        })
+    }
+
+    /// Returns the size of the largest fixed-length field which is read into the buffer.
+    pub fn max_len(&self, options: &ReadOptions) -> usize {
+        self.fields
+            .iter()
+            .filter(|field| !(options.seekable && field.byte_pattern().is_skip()))
+            .filter(|field| !field.is_boxed())
+            .map(|field| field.byte_pattern().len())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the size of the largest fixed-length field read into the buffer of the struct or
+    /// of any nested struct, which share a scratch buffer.
+    fn scratch_len(&self, options: &ReadOptions) -> usize {
+        self.fields
+            .iter()
+            .filter_map(|field| field.byte_pattern().nested())
+            .map(|body| body.scratch_len(options))
+            .fold(self.max_len(options), usize::max)
+    }
+
+    /// Returns the span of the first `@reader(READER)` of the fields or of nested structs.
+    pub fn reader_span(&self) -> Option<Span> {
+        self.fields.iter().find_map(|field| {
+            field
+                .reader_span()
+                .or_else(|| field.byte_pattern().nested()?.reader_span())
+        })
+    }
+
+    /// Returns `true` if any field or field of a nested struct is read up to a delimiter,
+    /// which requires a `BufRead` reader.
+    pub fn is_delimited(&self) -> bool {
+        self.fields.iter().any(|field| {
+            let pattern = field.byte_pattern();
+            pattern.delimiter().is_some() || pattern.nested().is_some_and(Self::is_delimited)
+        })
+    }
+
+    /// Returns the span of the first `seek(N)` of the fields.
+    pub fn seek_span(&self) -> Option<Span> {
+        self.fields.iter().find_map(HexStructField::seek_span)
+    }
+
+    pub fn path(&self) -> &Target {
+        &self.path
+    }
+    pub fn is_tuple(&self) -> bool {
+        matches!(self.path, Target::Tuple(_))
+    }
+    pub fn fields(&self) -> &Punctuated<HexStructField, Comma> {
+        &self.fields
+    }
+
+    /// Reads the fields into their bindings.
+    pub fn to_field_tokens(&self, options: &ReadOptions, stream: &mut TokenStream) {
+        for field in &self.fields {
+            field.to_read_tokens(options, stream);
+        }
+    }
+
+    /// Returns an expression instantiating the struct from the bindings of its fields,
+    /// with its path prefixed by `prefix` (used for enum variants).
+    pub fn instantiation(&self, prefix: Option<&Path>) -> TokenStream {
+        let HexStructBody {
+            attrs,
+            path,
+            fields,
+            dot2_token,
+            rest,
+            ..
+        } = self;
+
+        let mut struct_stream = TokenStream::new();
+        let struct_stream = &mut struct_stream;
+        {
+            // struct fields
+            for pair in fields.pairs() {
+                let field = pair.value();
+                let comma = pair.punct();
+
+                if !field.is_struct_member() {
+                    continue;
+                } else {
+                    field.to_instantiation_tokens(struct_stream);
+                    comma.to_tokens(struct_stream);
+                }
+            }
+            // .. rest
+            dot2_token.to_tokens(struct_stream);
+            rest.to_tokens(struct_stream);
+        }
+
+        if self.is_tuple() {
+            // elements in the order of their indices, which were all checked to be given
+            let mut elems: Vec<_> = fields
+                .iter()
+                .filter_map(|field| Some((field.tuple_index()?, field.binding_ident()?)))
+                .collect();
+            elems.sort_by_key(|(index, _)| *index);
+            let elems = elems.iter().map(|(_, binding)| binding);
+            return quote_spanned!(path.span()=>
+                #(#attrs)* (#(#elems,)*)
+            );
+        }
+
+        // errors about the instantiation, such as missing fields, point at the variant rather
+        // than at the enum
+        let prefix = prefix.map(|prefix| {
+            quote!(#prefix::)
+                .into_iter()
+                .map(|mut token| {
+                    token.set_span(path.span());
+                    token
+                })
+                .collect::<TokenStream>()
+        });
+        quote!(
+            #(#attrs)* #prefix #path { #struct_stream }
+        )
+    }
+}
+
+/// Parses a field of a tuple, inserting the type of the element it's named after unless it
+/// gives a type itself: `0: le "____"` becomes `0: u16 @ le "____"`.
+fn parse_tuple_field(input: ParseStream, elems: &[Type]) -> Result<HexStructField> {
+    let attrs = Attribute::parse_outer(input)?;
+    let member: Option<Member> = match input.parse::<Option<Token![_]>>()? {
+        Some(_) => None,
+        None => Some(input.parse()?),
+    };
+    let colon: Colon = input.parse()?;
+    let mut rest = TokenStream::new();
+    while !input.is_empty() && !input.peek(Token![,]) {
+        input.parse::<TokenTree>()?.to_tokens(&mut rest);
+    }
+
+    let ty = match &member {
+        None => None,
+        Some(Member::Unnamed(index)) => match elems.get(index.index as usize) {
+            Some(ty) => Some(ty),
+            None => {
+                return Err(syn::Error::new(
+                    index.span,
+                    format!(
+                        "the tuple has {} elements, found field `{}`",
+                        elems.len(),
+                        index.index
+                    ),
+                ))
+            }
+        },
+        Some(member) => {
+            return Err(syn::Error::new(
+                member.span(),
+                "fields of tuples are named by their index",
+            ))
+        }
+    };
+    let is_typed = |rest: ParseStream| -> Result<()> {
+        rest.parse::<Type>()?;
+        rest.parse::<Token![@]>()?;
+        rest.parse::<TokenStream>()?;
+        Ok(())
+    };
+    let typed = match ty.and_then(FieldType::convertible) {
+        _ if is_typed.parse2(rest.clone()).is_ok() => quote!(),
+        Some(true) => quote!(#ty @?),
+        Some(false) => quote!(#ty @),
+        None => quote!(),
+    };
+    let member = match &member {
+        Some(member) => quote!(#member),
+        None => quote!(_),
+    };
+    syn::parse2(quote!(#(#attrs)* #member #colon #typed #rest))
 }
 
 impl ToTokens for HexStruct {
     fn to_tokens(&self, output_stream: &mut TokenStream) {
+        let options = ReadOptions {
+            is_async: self.asyncness.is_some(),
+            io: self.io.clone(),
+            seekable: self.seekable.is_some(),
+            mismatch: self.mismatch.clone(),
+            slice: self.slice,
+            scratch: self.scratch.is_some(),
+            collect: self.collect.is_some(),
+            // without the feature, `trace` is accepted but costs nothing
+            trace: self.trace.is_some() && cfg!(feature = "trace"),
+            trace_prefix: String::new(),
+        };
+        let io = options.io();
+
         let mut closure_stream = TokenStream::new();
-        self.brace.surround(&mut closure_stream, |stream| {
+        self.body.brace.surround(&mut closure_stream, |stream| {
             let HexStruct {
+                counted,
                 reader,
-                attrs,
-                path,
-                fields,
-                dot2_token,
-                rest,
+                body,
                 ..
             } = self;
 
             // setup
             let array_ident = internal_ident("ARRAY", reader.span());
-            let len = fields
-                .iter()
-                .map(|field| field.byte_pattern().len())
-                .max()
-                .unwrap_or_default();
+            let array = match &self.scratch {
+                // nested structs reuse the same buffer, so it must fit all of them
+                Some(scratch) => {
+                    let len = body.scratch_len(&options);
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let #array_ident: &mut [u8] = #scratch;
+                        if #array_ident.len() < #len {
+                            return Err(#io::Error::new(
+                                #io::ErrorKind::InvalidInput,
+                                format!(
+                                    "expected a scratch buffer of at least {} bytes, got {}",
+                                    #len,
+                                    #array_ident.len()
+                                ),
+                            ));
+                        }
+                    )
+                }
+                None => options.array(&array_ident, body.max_len(&options)),
+            };
 
             let reader_ident = internal_ident("READER", reader.span());
+            let source_ident = internal_ident("SOURCE", reader.span());
+            let count_ident = internal_ident("COUNT", reader.span());
+            // the reader returned by `threaded`
+            let (reader, rest) = match (options.slice, self.frame_len) {
+                (true, _) => (
+                    quote!(let #reader_ident: &[u8] = #reader;),
+                    quote!(&#reader_ident[#count_ident..]),
+                ),
+                // the whole struct is read at once and its fields are read from memory
+                (false, Some(frame_len)) => {
+                    let frame_ident = internal_ident("FRAME", reader.span());
+                    let read_frame = options.read_exact(&source_ident, quote!(&mut #frame_ident));
+                    (
+                        quote!(
+                            let mut #source_ident = #reader;
+                            #[allow(non_snake_case)]
+                            let mut #frame_ident = [0u8; #frame_len];
+                            #read_frame?;
+                            #[allow(non_snake_case)]
+                            let mut #reader_ident: &[u8] = &#frame_ident;
+                        ),
+                        quote!(#source_ident),
+                    )
+                }
+                (false, None) => (
+                    quote!(let mut #reader_ident = #reader;),
+                    quote!(#reader_ident),
+                ),
+            };
             quote!(
-                 use std::convert::TryInto;
+                 use ::core::convert::TryInto;
 
                  #[allow(non_snake_case)]
-                 let mut #reader_ident = #reader;
+                 #reader
 
-                 #[allow(non_snake_case)]
-                 let mut #array_ident: [u8; #len] = [0; #len];
+                 #array
+
+                 #[allow(non_snake_case, unused_mut)]
+                 let mut #count_ident: usize = 0;
             )
             .to_tokens(stream);
-
-            for field in fields {
-                field.to_tokens(stream);
+            if options.seekable {
+                // count at which the current reader was switched to, which seeks can't go before
+                let base_ident = internal_ident("BASE", Span::call_site());
+                quote!(
+                    #[allow(non_snake_case, unused_mut, unused_variables)]
+                    let mut #base_ident: usize = 0;
+                )
+                .to_tokens(stream);
             }
 
-            let mut struct_stream = TokenStream::new();
-            let struct_stream = &mut struct_stream;
-            {
-                // struct fields
-                for pair in fields.pairs() {
-                    let field = pair.value();
-                    let comma = pair.punct();
+            body.to_field_tokens(&options, stream);
 
-                    if !field.is_struct_member() {
-                        continue;
-                    } else {
-                        field.to_instantiation_tokens(struct_stream);
-                        comma.to_tokens(struct_stream);
+            if self.exact.is_some() {
+                // the input must end after the last field
+                match (options.slice, self.frame_len) {
+                    (true, _) => quote!(
+                        if #reader_ident.len() > #count_ident {
+                            return Err(#io::Error::new(
+                                #io::ErrorKind::InvalidData,
+                                format!(
+                                    "expected the end of the input, got {} more bytes",
+                                    #reader_ident.len() - #count_ident
+                                ),
+                            ));
+                        }
+                    ),
+                    (false, frame_len) => {
+                        let reader = match frame_len {
+                            Some(_) => &source_ident,
+                            None => &reader_ident,
+                        };
+                        let read = options.read_retrying(reader, quote!(&mut [0u8; 1]));
+                        quote!(
+                            if #read? > 0 {
+                                return Err(#io::Error::new(
+                                    #io::ErrorKind::InvalidData,
+                                    "expected the end of the input, got more bytes",
+                                ));
+                            }
+                        )
                     }
                 }
-                // .. rest
-                dot2_token.to_tokens(struct_stream);
-                rest.to_tokens(struct_stream);
+                .to_tokens(stream);
             }
 
-            // struct setup
-            quote!(
-                Ok(#(#attrs)* #path { #struct_stream })
-            )
+            // struct setup, with the error type given for structs without fields to read
+            let instantiation = body.instantiation(None);
+            let ok =
+                quote_spanned!(body.path().span()=> ::core::result::Result::<_, #io::Error>::Ok);
+            match (counted, &self.threaded) {
+                (Some(_), Some(_)) => quote!(#ok((#instantiation, #count_ident, #rest))),
+                (Some(_), None) => quote!(#ok((#instantiation, #count_ident))),
+                (None, Some(_)) => quote!(
+                    let _ = #count_ident;
+                    #ok((#instantiation, #rest))
+                ),
+                (None, None) => quote!(
+                    let _ = #count_ident;
+                    #ok(#instantiation)
+                ),
+            }
             .to_tokens(stream);
         });
 
-        quote!(
-            (|| { #closure_stream })()
-        )
+        if self.collect.is_some() {
+            // mismatches are pushed to a list returned along with any error which stopped reading
+            let errors_ident = internal_ident("ERRORS", Span::call_site());
+            let read = match &self.asyncness {
+                Some(_) => quote!(async { #closure_stream }.await),
+                None => call_once(quote!(|| -> ::core::result::Result<_, #io::Error> {
+                    #closure_stream
+                })),
+            };
+            let map_err = self.error.as_ref().map(|error| {
+                quote!(.map_err(|errors| {
+                    let mut converted = vec![];
+                    for error in errors {
+                        converted.push(<#error as ::core::convert::From<#io::Error>>::from(error));
+                    }
+                    converted
+                }))
+            });
+            let collected = quote!(({
+                #[allow(non_snake_case)]
+                let mut #errors_ident = vec![];
+                let result = #read;
+                match result {
+                    Ok(_) if !#errors_ident.is_empty() => Err(#errors_ident),
+                    Ok(value) => Ok(value),
+                    Err(err) => {
+                        #errors_ident.push(err);
+                        Err(#errors_ident)
+                    }
+                }
+            }) #map_err);
+            match &self.asyncness {
+                Some(_) => quote!(async { #collected }),
+                None => collected,
+            }
+            .to_tokens(output_stream);
+            return;
+        }
+
+        let map_err = self
+            .error
+            .as_ref()
+            .map(|error| quote!(.map_err(<#error as ::core::convert::From<#io::Error>>::from)));
+        match (&self.asyncness, &map_err) {
+            (Some(_), Some(map_err)) => quote!(
+                async { async { #closure_stream }.await #map_err }
+            ),
+            (Some(_), None) => quote!(
+                async { #closure_stream }
+            ),
+            (None, Some(map_err)) => {
+                let read = call_once(quote!(|| -> ::core::result::Result<_, #io::Error> {
+                    #closure_stream
+                }));
+                quote!(#read #map_err)
+            }
+            (None, None) => call_once(quote!(|| { #closure_stream })),
+        }
         .to_tokens(output_stream);
     }
 }
+
+/// Removes the `#[NAME(...)]` attribute from `attrs`, returning its parsed argument.
+pub fn take_attribute<T: Parse>(attrs: &mut Vec<Attribute>, name: &str) -> Result<Option<T>> {
+    let mut value = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident(name) {
+            return true;
+        }
+        match attr.parse_args::<T>() {
+            Ok(_) if value.is_some() => {
+                result = Err(syn::Error::new_spanned(
+                    attr,
+                    format!("duplicate {} attribute", name),
+                ))
+            }
+            Ok(arg) => value = Some(arg),
+            Err(err) => result = Err(err),
+        }
+        false
+    });
+    result.map(|_| value)
+}
+
+/// Returns `true` if the input starts with the given keyword used as a modifier rather than
+/// as the start of the reader expression (`counted reader` but not `counted.as_ref()`).
+pub fn peek_modifier<T: Peek>(input: ParseStream, token: T) -> bool {
+    input.peek(token)
+        && !(input.peek2(Token![=>])
+            || input.peek2(Token![.])
+            || input.peek2(Token![?])
+            || input.peek2(Token![::])
+            || input.peek2(Token![as])
+            || input.peek2(Paren)
+            || input.peek2(Bracket))
+}
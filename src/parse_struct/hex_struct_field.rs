@@ -1,14 +1,28 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::parse::{Parse, ParseStream};
 
 use syn::{
+    parenthesized,
+    punctuated::Punctuated,
     spanned::Spanned,
-    token::{Colon, Underscore},
-    Attribute, Expr, Ident, Member, Result, Token,
+    token::{Colon, Comma, Paren, Underscore},
+    Attribute, Expr, Ident, Lit, LitInt, LitStr, Member, Meta, MetaNameValue, Result, Token,
 };
 
-use super::{byte_pattern::BytePattern, internal_ident};
+use super::{
+    bit_fields::BitFields,
+    byte_pattern::BytePattern,
+    field_type::{primitive_size, Endian, FieldType},
+    internal_ident, ReadOptions,
+};
+
+mod kw {
+    syn::custom_keyword!(validate);
+    syn::custom_keyword!(boxed);
+    syn::custom_keyword!(seek);
+    syn::custom_keyword!(reader);
+}
 
 #[derive(Debug)]
 enum HexIdent {
@@ -16,8 +30,12 @@ enum HexIdent {
     Underscore(Underscore),
 }
 impl HexIdent {
-    pub fn internal_ident(&self) -> Option<Ident> {
+    /// Returns the name of the variable holding the value of the field.
+    ///
+    /// Named fields are bound to their own names so that later fields can refer to them.
+    pub fn binding_ident(&self) -> Option<Ident> {
         match self {
+            Self::Member(Member::Named(ident)) => Some(ident.clone()),
             Self::Member(member) => Some(internal_ident(quote!(#member), member.span())),
             Self::Underscore(_) => None,
         }
@@ -43,12 +61,34 @@ impl Parse for HexIdent {
 
 #[derive(Debug)]
 pub struct HexStructField {
+    /// Reader of the field and of the following ones: `@reader(READER) FIELD: ...`.
+    reader: Option<Expr>,
     attrs: Vec<Attribute>,
+    /// Description of the field given by `#[note("...")]` or doc comments, used in errors.
+    note: Option<String>,
     member: HexIdent,
     colon: Colon,
+    /// Offset the reader is moved by before the field is read: `seek(N)`.
+    seek: Option<(kw::seek, i64)>,
+    field_type: Option<FieldType>,
     buffer_ident: Option<Ident>,
+    /// The bytes are split into bit fields bound to their names: `bits { NAME: WIDTH, ... } @`.
+    bits: Option<BitFields>,
+    /// The bytes are read into a `Box<[u8]>` instead of the buffer: `boxed BYTE_PATTERN`.
+    boxed: Option<kw::boxed>,
     byte_pattern: BytePattern,
+    /// Condition the bytes must meet in addition to the pattern: `PATTERN if GUARD`.
+    guard: Option<Expr>,
     expr: Option<Expr>,
+    /// Check of the field's value after the expression: `; validate CLOSURE`.
+    validate: Option<Expr>,
+    /// Position of the field in the input given by `#[order(N)]`.
+    order: Option<LitInt>,
+    /// The bytes are a byte order mark giving the byte order of later typed fields: `#[bom]`.
+    bom: bool,
+    /// Kind of the errors of mismatched bytes, guards and validations given by
+    /// `#[error_kind(KIND)]` on the field or on a struct containing it.
+    error_kind: Option<Ident>,
 }
 
 impl HexStructField {
@@ -63,7 +103,9 @@ impl HexStructField {
             colon,
             ..
         } = self;
-        let member_internal = member.internal_ident().unwrap();
+        // spanned on the pattern so that type mismatches point at the field's bytes
+        let mut member_internal = member.binding_ident().unwrap();
+        member_internal.set_span(self.byte_pattern.span());
 
         quote!(
             #(#attrs)*
@@ -72,12 +114,250 @@ impl HexStructField {
         .to_tokens(stream);
     }
 
+    /// Fails if the field can't be turned back into bytes by `write_struct!`.
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.byte_pattern.is_virtual() {
+            // not written
+            return Ok(());
+        }
+        if let Some((seek, _)) = &self.seek {
+            return Err(syn::Error::new(
+                seek.span,
+                "fields read after seeking can't be written",
+            ));
+        }
+        if let (Some(bits), HexIdent::Underscore(_)) = (&self.bits, &self.member) {
+            return Err(syn::Error::new(
+                bits.span(),
+                "bit fields can only be written from the bytes of struct members",
+            ));
+        }
+        if let Some(reader) = &self.reader {
+            return Err(syn::Error::new(
+                reader.span(),
+                "fields are written to a single writer and can't switch readers",
+            ));
+        }
+        if let Some(expr) = &self.expr {
+            return Err(syn::Error::new(
+                expr.span(),
+                "fields with expressions can't be written.\n\
+                 help: try using a typed field (`FIELD: TYPE @ [le|be] BYTE_PATTERN`) instead.",
+            ));
+        }
+        match (&self.member, self.byte_pattern.nested()) {
+            (HexIdent::Underscore(_), _) => self.byte_pattern.ensure_constant(),
+            (HexIdent::Member(_), Some(body)) => body
+                .fields()
+                .iter()
+                .try_for_each(HexStructField::ensure_writable),
+            (HexIdent::Member(_), None) => Ok(()),
+        }
+    }
+
+    /// Checks that typed fields have a byte order, using the one detected at runtime if a
+    /// `#[bom]` field was read before (`bom`) or else the default of the struct (`endian`).
+    pub fn resolve_endian(&mut self, bom: bool, endian: Option<Endian>) -> Result<()> {
+        if let Some(body) = self.byte_pattern.nested_mut() {
+            body.resolve_endian(bom, endian)?;
+        }
+        match &mut self.field_type {
+            Some(field_type) => {
+                match (bom, endian) {
+                    (true, _) => field_type.default_endian(Endian::Detected),
+                    (false, Some(endian)) => field_type.default_endian(endian),
+                    (false, None) => (),
+                }
+                field_type.check_endian()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the kind of mismatch errors to the one of the enclosing struct unless the field has
+    /// its own, which also applies to the fields of nested structs.
+    pub fn resolve_error_kind(&mut self, kind: Option<&Ident>) -> Result<()> {
+        if self.error_kind.is_none() {
+            self.error_kind = kind.cloned();
+        }
+        match self.byte_pattern.nested_mut() {
+            Some(body) => body.resolve_error_kind(self.error_kind.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the field's bytes to the writer used by `write_struct!`.
+    pub fn to_write_tokens(&self, stream: &mut TokenStream) {
+        let writer_ident = internal_ident("WRITER", self.byte_pattern().span());
+        let byte_pattern = self.byte_pattern();
+        let len = byte_pattern.len();
+        if byte_pattern.is_virtual() {
+            return;
+        }
+        let binding = match self.member.binding_ident() {
+            Some(binding) => binding,
+            None => {
+                let bytes = match byte_pattern {
+                    BytePattern::Skip { .. } => quote!([0u8; #len]),
+                    BytePattern::SkipTo { magic, .. } => quote!((#magic)[..]),
+                    BytePattern::CaseInsensitive { bstr, .. } => quote!(*#bstr),
+                    _ => quote!(#byte_pattern),
+                };
+                quote_spanned!(byte_pattern.span()=>
+                    #writer_ident.write_all(&#bytes)?;
+                )
+                .to_tokens(stream);
+                return;
+            }
+        };
+
+        if let Some(body) = byte_pattern.nested() {
+            let path = body.path();
+            let mut members = TokenStream::new();
+            let mut writes = TokenStream::new();
+            for field in body.fields() {
+                if field.is_written_member() {
+                    field.to_instantiation_tokens(&mut members);
+                    quote!(,).to_tokens(&mut members);
+                }
+                field.to_write_tokens(&mut writes);
+            }
+            let write = quote_spanned!(byte_pattern.span()=>
+                {
+                    #[allow(non_snake_case, non_shorthand_field_patterns)]
+                    let #path { #members .. } = #binding;
+                    #writes
+                }
+            );
+            if byte_pattern.record_count().is_some() {
+                quote_spanned!(byte_pattern.span()=>
+                    for #binding in #binding #write
+                )
+                .to_tokens(stream);
+            } else {
+                write.to_tokens(stream);
+            }
+            return;
+        }
+
+        let dynamic = byte_pattern.is_dynamic();
+        let bytes = match &self.field_type {
+            Some(field_type) => {
+                field_type.to_bytes(&binding, if dynamic { None } else { Some(len) })
+            }
+            None if dynamic || self.boxed.is_some() => quote!(&#binding[..]),
+            None => quote!(*#binding),
+        };
+        let check = if dynamic || self.field_type.as_ref().is_some_and(|t| t.is_string()) {
+            quote!()
+        } else if let Some(constant) = byte_pattern.constant() {
+            let constant_string = quote!(#constant).to_string();
+            quote!(
+                if bytes[..] != #constant[..] {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("expected `{}`, got `{:02X?}`", #constant_string, bytes),
+                    ));
+                }
+            )
+        } else {
+            let byte_pattern_string = format!("{}", byte_pattern);
+            quote!(
+                #[allow(dead_code, unreachable_patterns)]
+                match &bytes {
+                    #byte_pattern => (),
+                    _ => return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            format!("expected `{}`, got `{:02X?}`", #byte_pattern_string, bytes),
+                        ))
+                }
+            )
+        };
+        // delimited bytes are followed by the delimiter, which they can't contain
+        let (check, delimiter) = match byte_pattern.delimiter() {
+            Some(delimiter) => {
+                let binding_string = binding.to_string();
+                (
+                    quote!(
+                        let delimiter: u8 = #delimiter;
+                        if bytes.contains(&delimiter) {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidInput,
+                                format!(
+                                    "`{}` can't contain the delimiter {:#04X}",
+                                    #binding_string, delimiter
+                                ),
+                            ));
+                        }
+                    ),
+                    Some(quote!(#writer_ident.write_all(&[delimiter])?;)),
+                )
+            }
+            None => (check, None),
+        };
+        let write = quote_spanned!(byte_pattern.span()=>
+            {
+                let bytes = #bytes;
+                #check
+                #writer_ident.write_all(::core::convert::AsRef::<[u8]>::as_ref(&bytes))?;
+                #delimiter
+            }
+        );
+        if self.field_type.as_ref().is_some_and(FieldType::is_optional) {
+            // missing optional fields are left out
+            quote_spanned!(byte_pattern.span()=>
+                if let Some(#binding) = #binding #write
+            )
+            .to_tokens(stream);
+        } else {
+            write.to_tokens(stream);
+        }
+    }
+
+    /// Returns `true` for `Option<TYPE> @?` fields, which may be missing.
+    pub fn is_optional(&self) -> bool {
+        self.field_type.as_ref().is_some_and(FieldType::is_optional)
+    }
     pub fn is_struct_member(&self) -> bool {
         matches!(self.member, HexIdent::Member(_))
     }
+    /// Returns `true` for struct members written by `write_struct!`, which skips `virtual` ones.
+    pub fn is_written_member(&self) -> bool {
+        self.is_struct_member() && !self.byte_pattern.is_virtual()
+    }
     pub fn byte_pattern(&self) -> &BytePattern {
         &self.byte_pattern
     }
+    /// Returns the name of the variable holding the value of the field, if it's bound.
+    pub fn binding_ident(&self) -> Option<Ident> {
+        self.member.binding_ident()
+    }
+    /// Returns the index of fields named like the elements of a tuple (`0: PATTERN`).
+    pub fn tuple_index(&self) -> Option<usize> {
+        match &self.member {
+            HexIdent::Member(Member::Unnamed(index)) => Some(index.index as usize),
+            _ => None,
+        }
+    }
+    /// Returns `true` for `#[bom]` fields.
+    pub fn is_bom(&self) -> bool {
+        self.bom
+    }
+    pub fn is_boxed(&self) -> bool {
+        self.boxed.is_some()
+    }
+    /// Returns the span of the reader of `@reader(READER)`.
+    pub fn reader_span(&self) -> Option<Span> {
+        self.reader.as_ref().map(Spanned::span)
+    }
+    /// Returns the span of the first `seek(N)` of the field or of the fields of a nested struct.
+    pub fn seek_span(&self) -> Option<Span> {
+        match (&self.seek, self.byte_pattern.nested()) {
+            (Some((seek, _)), _) => Some(seek.span),
+            (None, Some(body)) => body.fields().iter().find_map(HexStructField::seek_span),
+            (None, None) => None,
+        }
+    }
     fn reader_ident(&self) -> Ident {
         internal_ident("READER", self.byte_pattern().span())
     }
@@ -92,87 +372,1245 @@ impl HexStructField {
     }
 }
 
-impl ToTokens for HexStructField {
-    fn to_tokens(&self, stream: &mut TokenStream) {
+impl HexStructField {
+    /// Reads the field from the reader used by `parse_struct!` and binds its value.
+    pub fn to_read_tokens(&self, options: &ReadOptions, stream: &mut TokenStream) {
         let reader_ident = self.reader_ident();
         let array_ident = self.array_ident();
         let buffer_ident = self.buffer_ident();
+        let count_ident = internal_ident("COUNT", self.byte_pattern().span());
+        let io = options.io();
+        let error_kind = match &self.error_kind {
+            Some(kind) => quote!(#io::ErrorKind::#kind),
+            None => quote!(#io::ErrorKind::InvalidData),
+        };
 
         let byte_pattern = self.byte_pattern();
         let len = byte_pattern.len();
         let byte_pattern_string = format!("{}", byte_pattern);
 
+        let base_ident = internal_ident("BASE", Span::call_site());
+        if let Some(reader) = &self.reader {
+            // shadows the previous reader for the rest of the struct
+            quote_spanned!(reader.span()=>
+                #[allow(non_snake_case, unused_mut)]
+                let mut #reader_ident = #reader;
+            )
+            .to_tokens(stream);
+            if options.seekable {
+                quote!(
+                    #[allow(unused_assignments)]
+                    let _: () = {
+                        #base_ident = #count_ident;
+                    };
+                )
+                .to_tokens(stream);
+            }
+        }
+
+        if let Some((seek, offset)) = &self.seek {
+            // slices are read at the count of bytes, so moving it is enough
+            let distance = offset.unsigned_abs();
+            let seek_reader = match options.slice {
+                true => quote!(),
+                false => {
+                    let seek_reader = options.seek(&reader_ident, *offset);
+                    quote!(#seek_reader?;)
+                }
+            };
+            let move_count = if *offset < 0 {
+                // bytes read from the current reader, which slices never switch
+                let read = match options.seekable {
+                    true => quote!((#count_ident - #base_ident)),
+                    false => quote!(#count_ident),
+                };
+                quote!(
+                    if (#read as u64) < #distance {
+                        return Err(#io::Error::new(
+                            #io::ErrorKind::InvalidInput,
+                            format!(
+                                "can't seek back {} bytes after reading {}",
+                                #distance, #read
+                            ),
+                        ));
+                    }
+                    #seek_reader
+                    #count_ident -= #distance as usize;
+                )
+            } else {
+                let ensure = options.slice.then(|| {
+                    let ensure = options.ensure_remaining(
+                        &reader_ident,
+                        &count_ident,
+                        quote!(#distance as usize),
+                    );
+                    quote!(#ensure?;)
+                });
+                quote!(
+                    #ensure
+                    #seek_reader
+                    #count_ident += #distance as usize;
+                )
+            };
+            quote_spanned!(seek.span=>
+                let _: () = {
+                    #move_count
+                };
+            )
+            .to_tokens(stream);
+        }
+
+        let start_ident = internal_ident("START", byte_pattern.span());
+        let trace_name = match &self.member {
+            HexIdent::Member(member) => format!("{}{}", options.trace_prefix, quote!(#member)),
+            HexIdent::Underscore(_) => {
+                format!("{}_: {}", options.trace_prefix, byte_pattern_string)
+            }
+        };
+        if options.trace {
+            quote!(
+                #[allow(non_snake_case, unused_variables)]
+                let #start_ident = #count_ident;
+            )
+            .to_tokens(stream);
+        }
+        let trace = options.trace(&trace_name, &start_ident, &buffer_ident);
+        let trace_skipped = options.trace_skipped(&trace_name, &start_ident, &count_ident);
+
         let value = {
             use HexIdent::*;
             match (&self.member, &self.expr) {
-                (Underscore(_), None) => quote!(),           // only check padding
-                (Member(_), None) => quote!(*#buffer_ident), // assign bytes
-                (_, Some(expr)) => quote!(#expr),            // use provided expression
+                (Underscore(_), None) => quote!(), // only check padding
+                (Member(_), None) => match &self.field_type {
+                    Some(field_type) => field_type.conversion(&buffer_ident, &io), // convert bytes
+                    None if byte_pattern.is_virtual() => quote!(Default::default()), // no bytes
+                    None if self.boxed.is_some() => quote!(#buffer_ident),         // move `Box`
+                    None if byte_pattern.is_dynamic() => quote!(#buffer_ident),    // move `Vec`
+                    None if options.slice => quote!(#buffer_ident),                // borrow bytes
+                    None => quote!(*#buffer_ident),                                // assign bytes
+                },
+                (_, Some(expr)) => quote!(#expr), // use provided expression
+            }
+        };
+
+        // the guard is checked with the value bound to the field's name
+        let value = match &self.guard {
+            Some(guard) => {
+                let member = &self.member;
+                let member_string = quote!(#member).to_string();
+                let guard_string = quote!(#guard).to_string();
+                let fail = options.mismatch(quote!(#io::Error::new(
+                    #error_kind,
+                    format!("`{}` doesn't meet `if {}`", #member_string, #guard_string),
+                )));
+                let guard_check = quote!(
+                    if !(#guard) {
+                        #fail;
+                    }
+                );
+                match self.member.binding_ident() {
+                    Some(binding) => quote!({
+                        #[allow(non_snake_case)]
+                        let #binding = #value;
+                        #guard_check
+                        #binding
+                    }),
+                    None => quote!({
+                        #guard_check
+                        #value
+                    }),
+                }
+            }
+            None => value,
+        };
+
+        // the closure is passed to a generic function so that its parameter is inferred as a
+        // reference to the value
+        let value = match (&self.validate, self.member.binding_ident()) {
+            (Some(validate), Some(binding)) => {
+                let member = &self.member;
+                let member_string = quote!(#member).to_string();
+                let validate_string = quote!(#validate).to_string();
+                let validate_ident = internal_ident("VALIDATE", validate.span());
+                let fail = options.mismatch(quote!(#io::Error::new(
+                    #error_kind,
+                    format!("`{}` failed validation `{}`", #member_string, #validate_string),
+                )));
+                quote!({
+                    #[allow(non_snake_case)]
+                    let #binding = #value;
+                    #[allow(non_snake_case)]
+                    fn #validate_ident<T, F: FnOnce(&T) -> bool>(value: &T, validate: F) -> bool {
+                        validate(value)
+                    }
+                    if !#validate_ident(&#binding, #validate) {
+                        #fail;
+                    }
+                    #binding
+                })
             }
+            _ => value,
         };
 
-        let member_ident = match self.member.internal_ident() {
+        let member_ident = match self.member.binding_ident() {
             Some(member_internal) => quote!(#member_internal),
             None => quote!(_: ()), // assert it's empty
         };
 
-        quote_spanned!(byte_pattern.span()=>
-            let #member_ident = {
-                #reader_ident.read_exact(&mut #array_ident[0..#len])?;
+        let max_check = byte_pattern.max().map(|max| {
+            let len_ident = internal_ident("LEN", byte_pattern.span());
+            let member = &self.member;
+            let member_string = quote!(#member).to_string();
+            quote!(
+                if #len_ident > #max {
+                    return Err(#io::Error::new(
+                        #io::ErrorKind::InvalidData,
+                        format!(
+                            "length of `{}` exceeds the maximum of {}, got {}",
+                            #member_string, #max, #len_ident
+                        ),
+                    ));
+                }
+            )
+        });
 
+        if let (Some(count), Some(body)) = (byte_pattern.record_count(), byte_pattern.nested()) {
+            // records are parsed like nested structs in a loop
+            let array = options.array(&array_ident, body.max_len(options));
+            let len_ident = internal_ident("LEN", byte_pattern.span());
+            let records_ident = internal_ident("RECORDS", byte_pattern.span());
+            let member = &self.member;
+            let member_string = quote!(#member).to_string();
+            let mut fields_stream = TokenStream::new();
+            let nested = options.nested(&format!("{}[]", member_string));
+            body.to_field_tokens(&nested, &mut fields_stream);
+            let instantiation = body.instantiation(None);
+            quote_spanned!(byte_pattern.span()=>
                 #[allow(non_snake_case)]
-                let #buffer_ident: &[u8; #len] = #array_ident[0..#len].try_into().unwrap();
+                let #member_ident = {
+                    #[allow(non_snake_case, clippy::unnecessary_fallible_conversions)]
+                    let #len_ident: usize = (#count).try_into().map_err(|_| {
+                        #io::Error::new(
+                            #io::ErrorKind::InvalidData,
+                            format!("length of `{}` doesn't fit in `usize`", #member_string),
+                        )
+                    })?;
+                    #max_check
 
-                #[allow(dead_code)]
-                match #buffer_ident {
-                    #byte_pattern => (),
-                    _ => return Err(std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            format!("expected `{}`, got `{:02X?}`", #byte_pattern_string, #buffer_ident),
-                        ))
+                    #[allow(non_snake_case)]
+                    let mut #records_ident = vec![];
+                    for _ in 0..#len_ident {
+                        #array
+
+                        #fields_stream
+                        #records_ident.push(#instantiation);
+                    }
+                    #records_ident
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let Some(body) = byte_pattern.nested() {
+            // nested fields share the reader but have their own buffer
+            let array = options.array(&array_ident, body.max_len(options));
+            let member = &self.member;
+            let mut fields_stream = TokenStream::new();
+            body.to_field_tokens(
+                &options.nested(&quote!(#member).to_string()),
+                &mut fields_stream,
+            );
+            let instantiation = body.instantiation(None);
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #array
+
+                    #fields_stream
+                    #instantiation
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        let read_array = match options.slice {
+            true => options.ensure_remaining(&reader_ident, &count_ident, quote!(#len)),
+            false => options.read_exact(&reader_ident, quote!(&mut #array_ident[0..#len])),
+        };
+        if let Some(dynamic_len) = byte_pattern.dynamic_len() {
+            let len_ident = internal_ident("LEN", byte_pattern.span());
+            let read_vec = match options.slice {
+                true => {
+                    let ensure =
+                        options.ensure_remaining(&reader_ident, &count_ident, quote!(#len_ident));
+                    quote!(
+                        #ensure?;
+                        #[allow(non_snake_case)]
+                        let #buffer_ident: &[u8] =
+                            &#reader_ident[#count_ident..#count_ident + #len_ident];
+                    )
+                }
+                false => {
+                    let read_vec = options.read_exact(&reader_ident, quote!(&mut #buffer_ident));
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let mut #buffer_ident = vec![0u8; #len_ident];
+                        #read_vec?;
+                    )
                 }
+            };
+            let member = &self.member;
+            let member_string = quote!(#member).to_string();
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #[allow(non_snake_case, clippy::unnecessary_fallible_conversions)]
+                    let #len_ident: usize = (#dynamic_len).try_into().map_err(|_| {
+                        #io::Error::new(
+                            #io::ErrorKind::InvalidData,
+                            format!("length of `{}` doesn't fit in `usize`", #member_string),
+                        )
+                    })?;
+                    #max_check
+
+                    #read_vec
+                    #count_ident += #len_ident;
+                    #trace
+
+                    #value
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let Some(delimiter) = byte_pattern.delimiter() {
+            let delimiter_ident = internal_ident("DELIMITER", byte_pattern.span());
+            let len_ident = internal_ident("LEN", byte_pattern.span());
+            let member = &self.member;
+            let member_string = quote!(#member).to_string();
+            let missing = quote!(#io::Error::new(
+                #io::ErrorKind::InvalidData,
+                format!(
+                    "expected `{}` to end with {:#04X}, got the end of the input",
+                    #member_string, #delimiter_ident
+                ),
+            ));
+            let read_until = match options.slice {
+                true => quote!(
+                    #[allow(non_snake_case)]
+                    let #len_ident = #reader_ident[#count_ident..]
+                        .iter()
+                        .position(|byte| *byte == #delimiter_ident)
+                        .ok_or_else(|| #missing)?;
+                    #[allow(non_snake_case)]
+                    let #buffer_ident: &[u8] =
+                        &#reader_ident[#count_ident..#count_ident + #len_ident];
+                    #count_ident += #len_ident + 1;
+                ),
+                false => {
+                    let read_until = options.read_until(
+                        &reader_ident,
+                        &delimiter_ident,
+                        quote!(&mut #buffer_ident),
+                    );
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let mut #buffer_ident = vec![];
+                        #count_ident += #read_until?;
+                        if #buffer_ident.pop() != Some(#delimiter_ident) {
+                            return Err(#missing);
+                        }
+                    )
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #[allow(non_snake_case)]
+                    let #delimiter_ident: u8 = #delimiter;
+                    #read_until
+                    #trace
 
+                    #value
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let BytePattern::Remaining(rest) = byte_pattern {
+            let read_rest = match options.slice {
+                true => quote!(
+                    #[allow(non_snake_case)]
+                    let #buffer_ident: &[u8] = &#reader_ident[#count_ident..];
+                    #count_ident = #reader_ident.len();
+                ),
+                false => {
+                    let read_rest = options.read_to_end(&reader_ident, quote!(&mut #buffer_ident));
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let mut #buffer_ident = vec![];
+                        #count_ident += #read_rest?;
+                    )
+                }
+            };
+            quote_spanned!(rest.span=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #read_rest
+                    #trace
+
+                    #value
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if byte_pattern.is_rest() {
+            let read_rest = match options.slice {
+                true => quote!(#reader_ident.len() - #count_ident),
+                false => {
+                    let read_rest = options.read_to_end(&reader_ident, quote!(&mut vec![]));
+                    quote!(#read_rest?)
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                let _: () = {
+                    #count_ident += #read_rest;
+                };
+                #trace_skipped
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if byte_pattern.is_virtual() {
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = #value;
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let Some(magic) = byte_pattern.magic() {
+            let magic_ident = internal_ident("MAGIC", byte_pattern.span());
+            let magic_string = quote!(#magic).to_string();
+            let missing = quote!(#io::Error::new(
+                #io::ErrorKind::UnexpectedEof,
+                format!("expected `{}` before the end of the input", #magic_string),
+            ));
+            let skip_to = match options.slice {
+                true => quote!(
+                    let position = match #magic_ident.len() {
+                        0 => Some(0),
+                        len => #reader_ident[#count_ident..]
+                            .windows(len)
+                            .position(|window| window == #magic_ident),
+                    };
+                    #count_ident += position.ok_or_else(|| #missing)? + #magic_ident.len();
+                ),
+                false => {
+                    // the last bytes read are compared with the magic after each byte
+                    let read = options.read_retrying(&reader_ident, quote!(&mut byte));
+                    quote!(
+                        let mut window = vec![];
+                        while window != #magic_ident {
+                            let mut byte = [0u8; 1];
+                            if #read? == 0 {
+                                return Err(#missing);
+                            }
+                            #count_ident += 1;
+                            if window.len() == #magic_ident.len() {
+                                window.remove(0);
+                            }
+                            window.push(byte[0]);
+                        }
+                    )
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                let _: () = {
+                    #[allow(non_snake_case)]
+                    let #magic_ident: &[u8] = &(#magic)[..];
+                    #skip_to
+                };
+                #trace_skipped
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let Some(alignment) = byte_pattern.alignment() {
+            // padding up to the next multiple of the alignment
+            let pad_ident = internal_ident("PAD", byte_pattern.span());
+            let read_pad = match options.slice {
+                true => options.ensure_remaining(&reader_ident, &count_ident, quote!(#pad_ident)),
+                false => {
+                    let read_pad =
+                        options.read_exact(&reader_ident, quote!(&mut #buffer_ident[..#pad_ident]));
+                    quote!({
+                        #[allow(non_snake_case)]
+                        let mut #buffer_ident = [0u8; #alignment];
+                        #read_pad
+                    })
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                let _: () = {
+                    #[allow(non_snake_case)]
+                    let #pad_ident = (#alignment - #count_ident % #alignment) % #alignment;
+                    #read_pad?;
+                    #count_ident += #pad_ident;
+                };
+                #trace_skipped
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if byte_pattern.is_skip() {
+            let skip = match options.seekable && !options.slice {
+                true => options.seek(&reader_ident, len as i64),
+                false => read_array,
+            };
+            quote_spanned!(byte_pattern.span()=>
+                let _: () = {
+                    #skip?;
+                    #count_ident += #len;
+                };
+                #trace_skipped
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        let mismatch = match &options.mismatch {
+            Some(ty) => {
+                let member = &self.member;
+                let member_string = quote!(#member).to_string();
+                quote!(#io::Error::from(#ty {
+                    field: #member_string,
+                    expected: #byte_pattern_string,
+                    actual: #buffer_ident.to_vec(),
+                }))
+            }
+            None => match &self.note {
+                Some(note) => {
+                    let member = &self.member;
+                    let member_string = quote!(#member).to_string();
+                    quote!(#io::Error::new(
+                        #error_kind,
+                        format!(
+                            "expected `{}` for {} (field `{}`), got `{:02X?}`",
+                            #byte_pattern_string, #note, #member_string, #buffer_ident
+                        ),
+                    ))
+                }
+                None => quote!(#io::Error::new(
+                    #error_kind,
+                    format!("expected `{}`, got `{:02X?}`", #byte_pattern_string, #buffer_ident),
+                )),
+            },
+        };
+        let fail = options.mismatch(mismatch);
+        if let Some(constant) = byte_pattern.constant() {
+            // the length of the constant is only known after expansion
+            let len_ident = internal_ident("LEN", byte_pattern.span());
+            let read_const = match options.slice {
+                true => {
+                    let ensure =
+                        options.ensure_remaining(&reader_ident, &count_ident, quote!(#len_ident));
+                    let buffer = options.try_into_array(
+                        quote!(&#reader_ident[#count_ident..#count_ident + #len_ident]),
+                        quote!(#len_ident),
+                    );
+                    quote!(
+                        #ensure?;
+                        #[allow(non_snake_case)]
+                        let #buffer_ident: &[u8; #len_ident] = #buffer;
+                    )
+                }
+                false => {
+                    let read_const = options.read_exact(&reader_ident, quote!(&mut #array_ident));
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let mut #array_ident = [0u8; #len_ident];
+                        #read_const?;
+                        #[allow(non_snake_case)]
+                        let #buffer_ident: &[u8; #len_ident] = &#array_ident;
+                    )
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #[allow(non_upper_case_globals)]
+                    const #len_ident: usize = #constant.len();
+                    #read_const
+                    #count_ident += #len_ident;
+                    if #buffer_ident[..] != #constant[..] {
+                        #fail;
+                    }
+                    #trace
+                    #value
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if self.boxed.is_some() {
+            // large fields are read to the heap instead of the buffer
+            let read_boxed = match options.slice {
+                true => {
+                    let ensure =
+                        options.ensure_remaining(&reader_ident, &count_ident, quote!(#len));
+                    quote!(
+                        #ensure?;
+                        #[allow(non_snake_case)]
+                        let #buffer_ident =
+                            #reader_ident[#count_ident..#count_ident + #len].to_vec().into_boxed_slice();
+                    )
+                }
+                false => {
+                    let read_boxed = options.read_exact(&reader_ident, quote!(&mut #buffer_ident));
+                    quote!(
+                        #[allow(non_snake_case)]
+                        let mut #buffer_ident = vec![0u8; #len].into_boxed_slice();
+                        #read_boxed?;
+                    )
+                }
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = {
+                    #read_boxed
+                    #count_ident += #len;
+
+                    #[allow(dead_code, unreachable_patterns)]
+                    match &#buffer_ident[..] {
+                        #byte_pattern => (),
+                        _ => #fail,
+                    }
+                    #trace
+                    #value
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        let buffer = match options.slice {
+            // the bytes were already counted
+            true => quote!(&#reader_ident[#count_ident - #len..#count_ident]),
+            false => quote!(&#array_ident[0..#len]),
+        };
+        let buffer = options.try_into_array(buffer, quote!(#len));
+        let buffer = quote!(
+            #[allow(non_snake_case)]
+            let #buffer_ident: &[u8; #len] = #buffer;
+        );
+        let check = quote_spanned!(byte_pattern.span()=>
+            #buffer
+
+            #[allow(dead_code, unreachable_patterns)]
+            match #buffer_ident {
+                #byte_pattern => (),
+                _ => #fail,
+            }
+            #trace
+        );
+
+        if self.field_type.as_ref().is_some_and(FieldType::is_optional) {
+            // no bytes at all result in `None`, but partial reads are still errors
+            let (read_first, read_rest) = match options.slice {
+                true => (
+                    options.ensure_remaining(&reader_ident, &count_ident, quote!(1)),
+                    options.ensure_remaining(&reader_ident, &count_ident, quote!(#len)),
+                ),
+                false => (
+                    options.read_exact(&reader_ident, quote!(&mut #array_ident[0..1])),
+                    options.read_exact(&reader_ident, quote!(&mut #array_ident[1..#len])),
+                ),
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let #member_ident = match #read_first {
+                    Err(err) if err.kind() == #io::ErrorKind::UnexpectedEof => None,
+                    Err(err) => return Err(err),
+                    Ok(_) => {
+                        #read_rest?;
+                        #count_ident += #len;
+                        #check
+                        Some(#value)
+                    }
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if self.bom {
+            // the byte order of later typed fields is bound along with the field
+            let big_endian_ident = internal_ident("BIG_ENDIAN", byte_pattern.span());
+            let member_ident = match self.member.binding_ident() {
+                Some(member_internal) => quote!(#member_internal),
+                None => quote!(()),
+            };
+            let value = match value.is_empty() {
+                true => quote!(()),
+                false => value,
+            };
+            let (little, big) = match len {
+                2 => (
+                    quote!([0x49, 0x49] | [0xFF, 0xFE]),
+                    quote!([0x4D, 0x4D] | [0xFE, 0xFF]),
+                ),
+                _ => (
+                    quote!([0xFF, 0xFE, 0x00, 0x00]),
+                    quote!([0x00, 0x00, 0xFE, 0xFF]),
+                ),
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case)]
+                let (#member_ident, #big_endian_ident) = {
+                    #read_array?;
+                    #count_ident += #len;
+                    #check
+                    let big_endian = match #buffer_ident {
+                        #little => false,
+                        #big => true,
+                        bytes => {
+                            return Err(#io::Error::new(
+                                #io::ErrorKind::InvalidData,
+                                format!("unknown byte order mark `{:02X?}`", bytes),
+                            ))
+                        }
+                    };
+                    (#value, big_endian)
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        if let Some(bits) = &self.bits {
+            // the bit fields are bound along with the field, for its expression and later fields
+            let fields_ident = internal_ident("FIELDS", bits.span());
+            let pattern = bits.pattern();
+            let extract = bits.extract(&buffer_ident);
+            let member_ident = match self.member.binding_ident() {
+                Some(member_internal) => quote!(#member_internal),
+                None => quote!(()),
+            };
+            let value = match value.is_empty() {
+                true => quote!(()),
+                false => value,
+            };
+            quote_spanned!(byte_pattern.span()=>
+                #[allow(non_snake_case, unused_variables)]
+                let (#member_ident, #pattern) = {
+                    #read_array?;
+                    #count_ident += #len;
+                    #check
+                    #[allow(non_snake_case)]
+                    let #fields_ident = #extract;
+                    #[allow(unused_variables)]
+                    let #pattern = #fields_ident;
+                    (#value, #fields_ident)
+                };
+            )
+            .to_tokens(stream);
+            return;
+        }
+
+        quote_spanned!(byte_pattern.span()=>
+            #[allow(non_snake_case)]
+            let #member_ident = {
+                #read_array?;
+                #count_ident += #len;
+                #check
                 #value
             };
-        ).to_tokens(stream);
+        )
+        .to_tokens(stream);
     }
 }
 
 impl Parse for HexStructField {
     fn parse(input: ParseStream) -> Result<Self> {
-        let attrs = Attribute::parse_inner(input)?;
+        let reader = match input.peek(Token![@]) && input.peek2(kw::reader) {
+            true => {
+                input.parse::<Token![@]>()?;
+                input.parse::<kw::reader>()?;
+                let content;
+                parenthesized!(content in input);
+                Some(content.parse()?)
+            }
+            false => None,
+        };
+        if input.peek(Token![#]) && input.peek2(Token![!]) {
+            return Err(input.error(
+                "inner attributes can't be given to fields.\n\
+                 help: use an outer attribute: `#[...]`",
+            ));
+        }
+        let mut attrs = Attribute::parse_outer(input)?;
+        let attr_endian = Endian::take_attribute(&mut attrs)?;
+        let note = take_note(&mut attrs)?;
+        let order = take_order(&mut attrs)?;
+        let bom = take_bom(&mut attrs)?;
+        let error_kind = take_error_kind(&mut attrs)?;
         let member = input.parse()?;
 
         let colon = input.parse()?;
-        let buffer_ident = if input.peek(Ident) {
-            let ident = input.parse()?;
+        let seek = match input.peek(kw::seek) && input.peek2(Paren) {
+            true => {
+                let seek: kw::seek = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                let minus: Option<Token![-]> = content.parse()?;
+                let offset: LitInt = content.parse()?;
+                let digits = offset.base10_digits();
+                let offset = match minus {
+                    Some(_) => format!("-{}", digits).parse::<i64>(),
+                    None => digits.parse::<i64>(),
+                }
+                .map_err(|_| syn::Error::new(offset.span(), "seek offsets must fit in an `i64`"))?;
+                Some((seek, offset))
+            }
+            false => None,
+        };
+        let mut field_type = FieldType::parse_typed(input)?;
+        let bits: Option<BitFields> = match BitFields::peek(input) {
+            true => {
+                let bits: BitFields = input.parse()?;
+                if field_type.is_some() {
+                    return Err(syn::Error::new(
+                        bits.span(),
+                        "typed fields can't be split into bit fields",
+                    ));
+                }
+                input.parse::<Token![@]>()?;
+                Some(bits)
+            }
+            false => None,
+        };
+        let buffer_ident = if field_type.is_none() && input.peek(Ident) && input.peek2(Token![@]) {
+            let ident: Ident = input.parse()?;
             input.parse::<Token![@]>()?;
             Some(ident)
         } else {
             None
         };
+        let boxed: Option<kw::boxed> = match input.peek(kw::boxed) && !input.peek2(Token![,]) {
+            true => Some(input.parse()?),
+            false => None,
+        };
+
+        let byte_pattern: BytePattern = input.parse()?;
+        if let Some(boxed) = &boxed {
+            if field_type.is_some() {
+                return Err(syn::Error::new(boxed.span(), "typed fields can't be boxed"));
+            }
+            if !matches!(
+                byte_pattern,
+                BytePattern::Array { .. } | BytePattern::HexString(_) | BytePattern::LitByteStr(_)
+            ) {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "only byte arrays, byte strings and hex strings can be boxed",
+                ));
+            }
+        }
+        if let Some(bits) = &bits {
+            if boxed.is_some()
+                || !matches!(
+                    byte_pattern,
+                    BytePattern::Array { .. }
+                        | BytePattern::HexString(_)
+                        | BytePattern::LitByteStr(_)
+                )
+            {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "bit fields can only be split from hex strings, byte strings and byte arrays",
+                ));
+            }
+            bits.check_len(byte_pattern.len())?;
+        }
+        if byte_pattern.nested().is_some() {
+            if let HexIdent::Underscore(underscore) = &member {
+                return Err(syn::Error::new(
+                    underscore.span(),
+                    "nested structs must be assigned to a struct member",
+                ));
+            }
+            if buffer_ident.is_some()
+                || input.peek(Token![=>])
+                || input.peek(Token![if])
+                || input.peek(Token![;])
+            {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "nested structs can't be bound, converted, guarded or validated",
+                ));
+            }
+        }
+        if byte_pattern.is_virtual() {
+            if let HexIdent::Underscore(underscore) = &member {
+                return Err(syn::Error::new(
+                    underscore.span(),
+                    "virtual fields must be assigned to a struct member",
+                ));
+            }
+            if field_type.is_some() || buffer_ident.is_some() || boxed.is_some() {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "virtual fields have no bytes to convert, bind or box.\n\
+                     help: give their value with `FIELD: virtual => EXPRESSION`",
+                ));
+            }
+        }
+        if byte_pattern.is_skip() {
+            if let HexIdent::Member(member) = &member {
+                return Err(syn::Error::new(
+                    member.span(),
+                    "skipped bytes can only be assigned to `_`",
+                ));
+            }
+            if buffer_ident.is_some()
+                || input.peek(Token![=>])
+                || input.peek(Token![if])
+                || input.peek(Token![;])
+            {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "skipped bytes can't be bound, converted, guarded or validated",
+                ));
+            }
+        }
+
+        match &mut field_type {
+            Some(field_type) if field_type.size().is_some() && byte_pattern.is_dynamic() => {
+                return Err(syn::Error::new(
+                    field_type.name().span(),
+                    "typed fields must have a fixed length",
+                ));
+            }
+            Some(field_type) if byte_pattern.constant().is_some() => {
+                return Err(syn::Error::new(
+                    field_type.name().span(),
+                    "typed fields can't be matched against constants.\n\
+                     help: use a binding (`FIELD: buf @ CONSTANT => EXPRESSION`) instead",
+                ));
+            }
+            Some(field_type) if field_type.is_optional() && byte_pattern.is_dynamic() => {
+                return Err(syn::Error::new(
+                    field_type.name().span(),
+                    "optional fields must have a fixed length",
+                ));
+            }
+            Some(field_type) => {
+                // checked once it's known whether a `#[bom]` field comes before
+                field_type.set_endian(attr_endian)?;
+                if let HexIdent::Underscore(underscore) = &member {
+                    return Err(syn::Error::new(
+                        underscore.span(),
+                        "typed fields must be assigned to a struct member",
+                    ));
+                }
+                match field_type.size() {
+                    Some(size) if size != byte_pattern.len() => {
+                        return Err(syn::Error::new(
+                            byte_pattern.span(),
+                            format!(
+                                "expected {} bytes for a `{}`, found {}",
+                                size,
+                                field_type.type_string(),
+                                byte_pattern.len()
+                            ),
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+            None if attr_endian.is_some() => {
+                return Err(syn::Error::new(
+                    member.span(),
+                    "byte order attributes can only be used with typed fields",
+                ));
+            }
+            None => (),
+        }
+
+        if bom {
+            if field_type.is_some()
+                || boxed.is_some()
+                || byte_pattern.is_dynamic()
+                || byte_pattern.constant().is_some()
+                || byte_pattern.nested().is_some()
+                || byte_pattern.is_skip()
+                || bits.is_some()
+            {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    "byte order marks must be untyped fields of a fixed length",
+                ));
+            }
+            if !matches!(byte_pattern.len(), 2 | 4) {
+                return Err(syn::Error::new(
+                    byte_pattern.span(),
+                    format!(
+                        "expected a byte order mark of 2 or 4 bytes, found {}",
+                        byte_pattern.len()
+                    ),
+                ));
+            }
+        }
+
+        let guard: Option<Expr> = match input.parse::<Option<Token![if]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
 
-        let byte_pattern = input.parse()?;
+        if field_type.is_some() && input.peek(Token![=>]) {
+            return Err(input.error(
+                "typed fields can't be followed by an expression\n\
+                 help: use a binding (`FIELD: buf @ PATTERN => EXPRESSION`) instead",
+            ));
+        }
 
-        let expr = if buffer_ident.is_some() || input.peek(Token![=>]) {
-            input.parse::<Token![=>]>().map_err(|_| {
-                input.error(
-                    "expected `=>` followed by an expression\n\
+        let expr: Option<Expr> =
+            if input.peek(Token![=>]) || (buffer_ident.is_some() && guard.is_none()) {
+                input.parse::<Token![=>]>().map_err(|_| {
+                    input.error(
+                        "expected `=>` followed by an expression\n\
                      help: remove the `@` binding to only match bytes",
-                )
-            })?;
-            Some(input.parse()?)
-        } else {
-            None
+                    )
+                })?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
+        let validate: Option<Expr> = match input.parse::<Option<Token![;]>>()? {
+            Some(_) => {
+                input.parse::<kw::validate>()?;
+                if let HexIdent::Underscore(underscore) = &member {
+                    return Err(syn::Error::new(
+                        underscore.span(),
+                        "validated fields must be assigned to a struct member",
+                    ));
+                }
+                Some(input.parse()?)
+            }
+            None => None,
         };
 
+        if let Some(buffer_ident) = &buffer_ident {
+            if !byte_pattern.is_dynamic() && byte_pattern.constant().is_none() {
+                for expr in guard.iter().chain(&expr) {
+                    check_conversions(expr.to_token_stream(), buffer_ident, byte_pattern.len())?;
+                }
+            }
+        }
+
         Ok(HexStructField {
+            reader,
             attrs,
+            note,
             member,
             colon,
+            seek,
+            field_type,
             buffer_ident,
+            bits,
+            boxed,
             byte_pattern,
+            guard,
             expr,
+            validate,
+            order,
+            bom,
+            error_kind,
         })
     }
 }
+
+/// Sorts fields by their `#[order(N)]` attributes, keeping the declaration order if there are
+/// none.
+pub fn sort_by_order(
+    fields: Punctuated<HexStructField, Comma>,
+) -> Result<Punctuated<HexStructField, Comma>> {
+    if fields.iter().all(|field| field.order.is_none()) {
+        return Ok(fields);
+    }
+    if let Some(field) = fields.iter().find(|field| field.order.is_none()) {
+        return Err(syn::Error::new(
+            field.member.span(),
+            "expected `#[order(N)]`, since other fields of the struct have one",
+        ));
+    }
+    let order =
+        |field: &HexStructField| -> usize { field.order.as_ref().unwrap().base10_parse().unwrap() };
+    let mut fields: Vec<HexStructField> = fields.into_iter().collect();
+    fields.sort_by_key(order);
+    for pair in fields.windows(2) {
+        if order(&pair[0]) == order(&pair[1]) {
+            return Err(syn::Error::new(
+                pair[1].order.span(),
+                format!("duplicate order {}", order(&pair[1])),
+            ));
+        }
+        if pair[0].byte_pattern.is_rest() {
+            return Err(syn::Error::new(
+                pair[0].order.span(),
+                format!(
+                    "`{}` must be the last field to be read",
+                    pair[0].byte_pattern
+                ),
+            ));
+        }
+    }
+    Ok(fields.into_iter().collect())
+}
+
+/// Removes the `#[order(N)]` attribute, returning its position.
+fn take_order(attrs: &mut Vec<Attribute>) -> Result<Option<LitInt>> {
+    let mut order = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("order") {
+            return true;
+        }
+        match attr.parse_args::<LitInt>() {
+            Ok(_) if order.is_some() => {
+                result = Err(syn::Error::new_spanned(attr, "duplicate order attribute"))
+            }
+            Ok(lit) => match lit.base10_parse::<usize>() {
+                Ok(_) => order = Some(lit),
+                Err(err) => result = Err(err),
+            },
+            Err(err) => result = Err(err),
+        }
+        false
+    });
+    result.map(|_| order)
+}
+
+/// Removes the `#[error_kind(KIND)]` attribute, returning the kind.
+fn take_error_kind(attrs: &mut Vec<Attribute>) -> Result<Option<Ident>> {
+    let mut kind = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("error_kind") {
+            return true;
+        }
+        match attr.parse_args::<Ident>() {
+            Ok(_) if kind.is_some() => {
+                result = Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate error_kind attribute",
+                ))
+            }
+            Ok(ident) => kind = Some(ident),
+            Err(err) => result = Err(err),
+        }
+        false
+    });
+    result.map(|_| kind)
+}
+
+/// Removes the `#[bom]` attribute, returning `true` if there was one.
+fn take_bom(attrs: &mut Vec<Attribute>) -> Result<bool> {
+    let mut bom = false;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("bom") {
+            return true;
+        }
+        if !attr.tokens.is_empty() {
+            result = Err(syn::Error::new_spanned(attr, "expected `#[bom]`"));
+        } else if bom {
+            result = Err(syn::Error::new_spanned(attr, "duplicate bom attribute"));
+        }
+        bom = true;
+        false
+    });
+    result.map(|_| bom)
+}
+
+/// Removes `#[note("...")]` attributes and doc comments, returning the description they give.
+fn take_note(attrs: &mut Vec<Attribute>) -> Result<Option<String>> {
+    let mut lines = vec![];
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        let line = if attr.path.is_ident("note") {
+            attr.parse_args::<LitStr>()
+        } else if attr.path.is_ident("doc") {
+            match attr.parse_meta() {
+                Ok(Meta::NameValue(MetaNameValue {
+                    lit: Lit::Str(lit), ..
+                })) => Ok(lit),
+                Ok(meta) => Err(syn::Error::new_spanned(meta, "expected `#[doc = \"...\"]`")),
+                Err(err) => Err(err),
+            }
+        } else {
+            return true;
+        };
+        match line {
+            Ok(line) => lines.push(line.value().trim().to_string()),
+            Err(err) => result = Err(err),
+        }
+        false
+    });
+    result.map(|_| Some(lines.join(" ")).filter(|note| !note.is_empty()))
+}
+
+/// Fails if the binding is converted with `TYPE::from_le_bytes` (or `from_be_bytes`,
+/// `from_ne_bytes`) of a primitive type whose size differs from the length of the pattern.
+fn check_conversions(stream: TokenStream, buffer_ident: &Ident, len: usize) -> Result<()> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    for (i, token) in tokens.iter().enumerate() {
+        let group = match token {
+            TokenTree::Group(group) => group,
+            _ => continue,
+        };
+        check_conversions(group.stream(), buffer_ident, len)?;
+        if group.delimiter() != Delimiter::Parenthesis || i < 4 {
+            continue;
+        }
+        let (ty, function) = match &tokens[i - 4..i] {
+            [TokenTree::Ident(ty), TokenTree::Punct(colon1), TokenTree::Punct(colon2), TokenTree::Ident(function)]
+                if colon1.as_char() == ':' && colon2.as_char() == ':' =>
+            {
+                (ty, function)
+            }
+            _ => continue,
+        };
+        let size = match (function.to_string().as_str(), primitive_size(ty)) {
+            ("from_le_bytes" | "from_be_bytes" | "from_ne_bytes", Some(size)) => size,
+            _ => continue,
+        };
+        let uses_buffer = group.stream().into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == *buffer_ident,
+            _ => false,
+        });
+        if uses_buffer && size != len {
+            return Err(syn::Error::new(
+                group.span(),
+                format!(
+                    "expected {} bytes for `{}::{}`, found {}",
+                    size, ty, function, len
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,248 @@
-use proc_macro2::{Ident, Span};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::quote;
 use std::fmt::Display;
+use syn::{Path, PathArguments, Type};
 
+mod bit_fields;
 mod byte_pattern;
+mod field_type;
+mod hex_enum;
 mod hex_struct;
 mod hex_struct_field;
 
-pub use hex_struct::HexStruct;
+pub use field_type::{Endian, FieldType};
+pub use hex_enum::HexEnum;
+pub use hex_struct::{HexSlice, HexStruct};
+pub use hex_struct_field::{sort_by_order, HexStructField};
 
-const INTERNAL_PREFIX: &str = "__hex_magic__FC9DC740_9AE7_4B27_A3B6_FAC53B953F22";
+/// Options changing how fields are read by `parse_struct!`.
+#[derive(Debug, Default, Clone)]
+pub struct ReadOptions {
+    /// Reads are awaited, for readers implementing `AsyncReadExt`.
+    pub is_async: bool,
+    /// Module providing `Error` and `ErrorKind` in place of `std::io`, given by `#[io(PATH)]`.
+    pub io: Option<Path>,
+    /// Skipped bytes are seeked over, for readers implementing `Seek`.
+    pub seekable: bool,
+    /// Type describing mismatched bytes, converted into `std::io::Error`,
+    /// given by `#[mismatch(TYPE)]`.
+    pub mismatch: Option<Type>,
+    /// Fields borrow from the `&[u8]` reader instead of copying bytes, used by `parse_slice!`.
+    pub slice: bool,
+    /// Fixed-length fields are read into a buffer given by the caller instead of an array on
+    /// the stack.
+    pub scratch: bool,
+    /// Mismatches are collected instead of returned, given by `collect`.
+    pub collect: bool,
+    /// Each field is printed to stderr after it's read, given by `trace` with the `trace`
+    /// feature enabled.
+    pub trace: bool,
+    /// Path of the nested struct the fields belong to in traces, such as `header.`.
+    pub trace_prefix: String,
+}
+
+impl ReadOptions {
+    /// Returns the options of the fields of a nested struct, which are traced with the
+    /// given prefix.
+    pub fn nested(&self, prefix: &str) -> Self {
+        Self {
+            trace_prefix: format!("{}{}.", self.trace_prefix, prefix),
+            ..self.clone()
+        }
+    }
+
+    /// Returns an expression reading all remaining bytes from `reader` into `buffer`,
+    /// evaluating to their number.
+    pub fn read_to_end(&self, reader: &Ident, buffer: TokenStream) -> TokenStream {
+        if self.is_async {
+            quote!(#reader.read_to_end(#buffer).await)
+        } else {
+            quote!(#reader.read_to_end(#buffer))
+        }
+    }
+
+    /// Returns a statement printing the name of a field, the offset it was read at and its
+    /// `bytes` when tracing.
+    pub fn trace(&self, field: &str, start: &Ident, bytes: &Ident) -> TokenStream {
+        if !self.trace {
+            return quote!();
+        }
+        quote!(
+            eprintln!(
+                "parse_struct: `{}` at {}: {:02X?}",
+                #field,
+                #start,
+                &#bytes[..]
+            );
+        )
+    }
+
+    /// Returns a statement printing the name of a field, the offset it was read at and the
+    /// number of bytes it skipped when tracing.
+    pub fn trace_skipped(&self, field: &str, start: &Ident, count: &Ident) -> TokenStream {
+        if !self.trace {
+            return quote!();
+        }
+        quote!(
+            eprintln!(
+                "parse_struct: `{}` at {}: skipped {} bytes",
+                #field,
+                #start,
+                #count - #start
+            );
+        )
+    }
+
+    /// Returns the path of the module providing `Error` and `ErrorKind`.
+    pub fn io(&self) -> TokenStream {
+        match &self.io {
+            Some(path) => quote!(#path),
+            None => quote!(std::io),
+        }
+    }
+
+    /// Returns the declaration of the buffer which fixed-length fields are read into,
+    /// which isn't needed when borrowing from a slice or reading into a scratch buffer.
+    pub fn array(&self, array: &Ident, len: usize) -> TokenStream {
+        if self.slice || self.scratch {
+            quote!()
+        } else {
+            quote!(
+                #[allow(non_snake_case)]
+                let mut #array: [u8; #len] = [0; #len];
+            )
+        }
+    }
+
+    /// Returns an expression failing with `UnexpectedEof` unless at least `len` bytes of the
+    /// slice `reader` follow the `count` bytes already read.
+    pub fn ensure_remaining(&self, reader: &Ident, count: &Ident, len: TokenStream) -> TokenStream {
+        let io = self.io();
+        quote!(
+            if #reader.len() - #count >= #len {
+                Ok(())
+            } else {
+                Err(#io::Error::new(
+                    #io::ErrorKind::UnexpectedEof,
+                    format!("expected {} more bytes, got {}", #len, #reader.len() - #count),
+                ))
+            }
+        )
+    }
+
+    /// Returns an expression converting the slice `bytes` into an array of `len` bytes (or a
+    /// reference to one), failing with `InvalidData` instead of panicking if the lengths differ.
+    pub fn try_into_array(&self, bytes: TokenStream, len: TokenStream) -> TokenStream {
+        let io = self.io();
+        quote!(
+            (#bytes).try_into().map_err(|_| {
+                #io::Error::new(
+                    #io::ErrorKind::InvalidData,
+                    format!("expected {} bytes, got {}", #len, (#bytes).len()),
+                )
+            })?
+        )
+    }
+
+    /// Returns a statement failing with the mismatch `error`, which is only recorded when
+    /// mismatches are collected so the following fields are still read.
+    pub fn mismatch(&self, error: TokenStream) -> TokenStream {
+        if self.collect {
+            let errors = internal_ident("ERRORS", Span::call_site());
+            quote!(#errors.push(#error))
+        } else {
+            quote!(return Err(#error))
+        }
+    }
+
+    /// Returns an expression moving `reader` by `offset` bytes, backwards if it's negative.
+    pub fn seek(&self, reader: &Ident, offset: i64) -> TokenStream {
+        let io = self.io();
+        let offset = Literal::i64_suffixed(offset);
+        if self.is_async {
+            quote!(#reader.seek(#io::SeekFrom::Current(#offset)).await)
+        } else {
+            quote!(#reader.seek(#io::SeekFrom::Current(#offset)))
+        }
+    }
+
+    /// Returns an expression appending bytes from `reader` to the `Vec` `buffer` up to and
+    /// including `delimiter`, evaluating to their number.
+    pub fn read_until(
+        &self,
+        reader: &Ident,
+        delimiter: &Ident,
+        buffer: TokenStream,
+    ) -> TokenStream {
+        if self.is_async {
+            quote!(#reader.read_until(#delimiter, #buffer).await)
+        } else {
+            quote!(#reader.read_until(#delimiter, #buffer))
+        }
+    }
+
+    /// Returns an expression reading bytes from `reader` into `buffer`, evaluating to their
+    /// number.
+    pub fn read(&self, reader: &Ident, buffer: TokenStream) -> TokenStream {
+        if self.is_async {
+            quote!(#reader.read(#buffer).await)
+        } else {
+            quote!(#reader.read(#buffer))
+        }
+    }
+
+    /// Returns an expression reading bytes from `reader` into `buffer` like `read`, retrying
+    /// reads interrupted by a signal as `read_exact` does.
+    pub fn read_retrying(&self, reader: &Ident, buffer: TokenStream) -> TokenStream {
+        let io = self.io();
+        let read = self.read(reader, buffer);
+        quote!(loop {
+            match #read {
+                Err(err) if matches!(err.kind(), #io::ErrorKind::Interrupted) => continue,
+                result => break result,
+            }
+        })
+    }
+
+    /// Returns an expression filling `buffer` with bytes from `reader`.
+    pub fn read_exact(&self, reader: &Ident, buffer: TokenStream) -> TokenStream {
+        if self.is_async {
+            quote!(#reader.read_exact(#buffer).await)
+        } else {
+            quote!(#reader.read_exact(#buffer))
+        }
+    }
+}
+
+/// Returns an expression calling `closure` once. Passing it to a function taking an `FnOnce`
+/// lets the result borrow from variables the closure captures, such as `&mut cursor` readers
+/// returned by `threaded`, which `FnMut` closures inferred from `(|| ...)()` can't return,
+/// while the captures stay borrows unlike with `move`.
+pub(crate) fn call_once(closure: TokenStream) -> TokenStream {
+    let once_ident = internal_ident("ONCE", Span::call_site());
+    quote!({
+        #[allow(non_snake_case)]
+        fn #once_ident<T, F: ::core::ops::FnOnce() -> T>(f: F) -> T {
+            f()
+        }
+        #once_ident(#closure)
+    })
+}
+
+pub(crate) const INTERNAL_PREFIX: &str = "__hex_magic__FC9DC740_9AE7_4B27_A3B6_FAC53B953F22";
+
+/// Writes the generic arguments of `path` with turbofishes (`Packet::<u16>`), as required by
+/// expressions, which type paths (`Packet<u16>`) don't have.
+pub(crate) fn turbofish(path: &mut Path) {
+    for segment in path.segments.iter_mut() {
+        if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            if args.colon2_token.is_none() {
+                args.colon2_token = Some(Default::default());
+            }
+        }
+    }
+}
 
-fn internal_ident<T: Display>(ident: T, span: Span) -> Ident {
+pub(crate) fn internal_ident<T: Display>(ident: T, span: Span) -> Ident {
     Ident::new(format!("{}_{}", INTERNAL_PREFIX, ident).as_str(), span)
 }
@@ -0,0 +1,110 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use syn::{
+    braced,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::{Brace, Comma},
+    Attribute, Expr, Path, Result, Token,
+};
+
+use crate::parse_struct::{internal_ident, sort_by_order, turbofish, Endian, HexStructField};
+
+/// Input of the `write_struct!` macro: `WRITER, VALUE => STRUCT { FIELDS }`.
+#[derive(Debug)]
+pub struct WriteStruct {
+    writer: Expr,
+    value: Expr,
+    path: Path,
+    brace: Brace,
+    fields: Punctuated<HexStructField, Comma>,
+}
+
+impl Parse for WriteStruct {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let writer = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let value = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let endian = take_endian(Attribute::parse_outer(input)?)?;
+        let mut path = input.parse()?;
+        turbofish(&mut path);
+        let content;
+        let brace = braced!(content in input);
+        let mut fields = sort_by_order(Punctuated::parse_terminated(&content)?)?;
+        for field in fields.iter_mut() {
+            field.resolve_endian(false, endian)?;
+            field.ensure_writable()?;
+        }
+
+        Ok(Self {
+            writer,
+            value,
+            path,
+            brace,
+            fields,
+        })
+    }
+}
+
+impl ToTokens for WriteStruct {
+    fn to_tokens(&self, output_stream: &mut TokenStream) {
+        let mut closure_stream = TokenStream::new();
+        self.brace.surround(&mut closure_stream, |stream| {
+            let WriteStruct {
+                writer,
+                value,
+                path,
+                fields,
+                ..
+            } = self;
+
+            // borrow struct members
+            let mut members = TokenStream::new();
+            for field in fields {
+                if field.is_written_member() {
+                    field.to_instantiation_tokens(&mut members);
+                    quote!(,).to_tokens(&mut members);
+                }
+            }
+
+            let writer_ident = internal_ident("WRITER", writer.span());
+            quote!(
+                #[allow(non_snake_case)]
+                let mut #writer_ident = #writer;
+
+                #[allow(non_snake_case, non_shorthand_field_patterns)]
+                let #path { #members .. } = #value;
+            )
+            .to_tokens(stream);
+
+            for field in fields {
+                field.to_write_tokens(stream);
+            }
+
+            quote!(Ok::<(), std::io::Error>(())).to_tokens(stream);
+        });
+
+        quote!(
+            (|| { #closure_stream })()
+        )
+        .to_tokens(output_stream);
+    }
+}
+
+/// Returns the default byte order given by an `#[endian(le|be)]` attribute, the only attribute
+/// `write_struct!` accepts on the struct.
+fn take_endian(attrs: Vec<Attribute>) -> Result<Option<Endian>> {
+    let mut endian = None;
+    for attr in attrs {
+        if !attr.path.is_ident("endian") {
+            return Err(syn::Error::new_spanned(attr, "expected `#[endian(le|be)]`"));
+        }
+        if endian.replace(attr.parse_args()?).is_some() {
+            return Err(syn::Error::new_spanned(attr, "duplicate endian attribute"));
+        }
+    }
+    Ok(endian)
+}
@@ -0,0 +1,115 @@
+//! Checks that the output of `hex!` doesn't depend on `std`, and that `parse_struct!` and
+//! `parse_enum!` can be used with `alloc` and a custom I/O module.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec, vec::Vec};
+use hex_magic::{hex, hex_u32, parse_enum, parse_struct};
+
+pub const MAGIC: [u8; 4] = hex!("7F454C46");
+pub const HEADER: [u8; 6] = hex!("7F454C46 0201", 6);
+pub const SLICE: &[u8] = hex!(slice rev "0201");
+pub const CONCAT: [u8; 6] = hex!("DEAD", MAGIC, 6);
+pub const VALUE: u32 = hex_u32!(be "DEADBEEF");
+
+pub fn is_elf(bytes: &[u8]) -> bool {
+    matches!(bytes, hex!("7F454C46 ..") | hex!("7F?? 4C46"))
+}
+
+/// Minimal replacement for `std::io`.
+pub mod io {
+    use alloc::string::{String, ToString};
+    use core::fmt::Display;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ErrorKind {
+        InvalidData,
+        UnexpectedEof,
+    }
+
+    #[derive(Debug)]
+    pub struct Error {
+        pub kind: ErrorKind,
+        pub message: String,
+    }
+
+    impl Error {
+        pub fn new<E: Display>(kind: ErrorKind, error: E) -> Self {
+            Self {
+                kind,
+                message: error.to_string(),
+            }
+        }
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    pub trait Read {
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error>;
+    }
+
+    impl Read for &[u8] {
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+            if buf.len() > self.len() {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "unexpected end of input",
+                ));
+            }
+            let (head, tail) = self.split_at(buf.len());
+            buf.copy_from_slice(head);
+            *self = tail;
+            Ok(())
+        }
+    }
+}
+
+pub struct Record {
+    pub version: u16,
+    pub name: String,
+    pub data: Vec<u8>,
+    pub flags: Option<u8>,
+}
+
+pub fn parse_record(bytes: &[u8]) -> Result<Record, io::Error> {
+    use io::Read;
+
+    parse_struct!(bytes => #[io(crate::io)] Record {
+        _: b"RC",
+        version: u16 @ le "____",
+        name: String @ nul "________",
+        _: align(4),
+        data: vec(version),
+        flags: Option<u8> @? "__",
+    })
+}
+
+pub fn check_record(bytes: &[u8]) -> Result<Record, Vec<io::Error>> {
+    use io::Read;
+
+    parse_struct!(collect bytes => #[io(crate::io)] Record {
+        _: b"RC",
+        version: u16 @ le "____" if version > 0,
+        name: String @ nul "________",
+        _: align(4),
+        data: vec(version),
+        flags: Option<u8> @? "__",
+    })
+}
+
+pub enum Chunk {
+    Header { version: u16 },
+    Data { data: Vec<u8> },
+}
+
+pub fn parse_chunk(bytes: &[u8]) -> Result<Chunk, io::Error> {
+    use io::Read;
+
+    parse_enum!(bytes => #[io(crate::io)] Chunk {
+        length: u16 @ le "____",
+        b"HD" => Header { version: u16 @ le "____" },
+        b"DT" => Data { data: vec(length) },
+    })
+}
@@ -0,0 +1,33 @@
+//! Parses a struct with nested fields and records using `parse_struct!(trace ...)`, printing
+//! each field read to stderr.
+
+use hex_magic::parse_struct;
+use std::io::Read;
+
+struct Inner {
+    a: u8,
+}
+
+struct Entry {
+    id: u8,
+}
+
+struct Outer {
+    version: u8,
+    inner: Inner,
+    entries: Vec<Entry>,
+}
+
+fn main() {
+    let bytes = [0x01, 0xFF, 0xAA, 0x10, 0x20];
+    let outer = parse_struct!(trace bytes.as_ref() => Outer {
+        version: u8 @ "01",
+        _: skip(1),
+        inner: Inner { a: u8 @ "__" },
+        entries: [Entry; 2] @ { id: u8 @ "__" },
+    })
+    .unwrap();
+    assert_eq!(outer.version, 1);
+    assert_eq!(outer.inner.a, 0xAA);
+    assert_eq!(outer.entries[1].id, 0x20);
+}
@@ -0,0 +1,21 @@
+use std::process::Command;
+
+#[test]
+fn prints_nested_field_names() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hex-magic-trace"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "parse_struct: `version` at 0: [01]",
+            "parse_struct: `_: skip(1)` at 1: skipped 1 bytes",
+            "parse_struct: `inner.a` at 2: [AA]",
+            "parse_struct: `entries[].id` at 3: [10]",
+            "parse_struct: `entries[].id` at 4: [20]",
+        ]
+    );
+}